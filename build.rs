@@ -0,0 +1,45 @@
+//! Builds the contracts the simulation tests deploy into `res/`, like `build.sh` does, so the
+//! tests never run against stale or missing wasm files.
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Directory and package name of each contract the simulation tests load from `res/`.
+/// `res/defi.wasm` and `res/standard_fungible_token.wasm` are prebuilt and committed.
+const CONTRACTS: &[(&str, &str)] = &[
+    ("synchro-token", "fungible-token"),
+    ("gov-contract", "gov"),
+    ("multisig-contract", "multisig"),
+    ("test-contract-market", "market"),
+    ("pair-stake-pool", "stake-pool"),
+];
+
+fn main() {
+    let root = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let root = Path::new(&root);
+    for (dir, _) in CONTRACTS {
+        println!("cargo:rerun-if-changed={}", root.join(dir).display());
+    }
+
+    // The outer build holds the lock of the default target directory.
+    let target_dir = root.join("target").join("sim-wasm");
+    let mut command = Command::new(env::var("CARGO").unwrap_or_else(|_| "cargo".to_string()));
+    command
+        .current_dir(root)
+        .args(&["build", "--target", "wasm32-unknown-unknown", "--release", "--target-dir"])
+        .arg(&target_dir)
+        // Flags of the outer build are meant for the host.
+        .env_remove("CARGO_ENCODED_RUSTFLAGS");
+    for (_, package) in CONTRACTS {
+        command.arg("-p").arg(package);
+    }
+    let status = command.status().expect("Failed to run cargo");
+    assert!(status.success(), "Building the contracts for the simulation tests failed");
+
+    for (_, package) in CONTRACTS {
+        let file = format!("{}.wasm", package.replace('-', "_"));
+        let built = target_dir.join("wasm32-unknown-unknown").join("release").join(&file);
+        fs::copy(&built, root.join("res").join(&file)).expect("Failed to copy the built contract");
+    }
+}
@@ -0,0 +1,303 @@
+use crate::*;
+use crate::utils::{integer_sqrt, MAX_POLL_OPTIONS};
+
+/// What a poll asks voters to choose between.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub enum PollKind {
+    /// Option 0 is yes and option 1 is no.
+    YesNo,
+    MultiChoice {
+        options: Vec<String>,
+        winning_rule: WinningRule,
+    },
+}
+
+impl Default for PollKind {
+    fn default() -> Self {
+        PollKind::YesNo
+    }
+}
+
+impl PollKind {
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if let PollKind::MultiChoice { options, .. } = self {
+            if options.len() < 2 || options.len() > MAX_POLL_OPTIONS {
+                return Err("ERR_NOT_VALID_NUMBER_OF_OPTIONS");
+            }
+            if options.iter().any(|option| option.is_empty()) {
+                return Err("ERR_EMPTY_OPTION");
+            }
+        }
+        Ok(())
+    }
+
+    pub fn num_options(&self) -> usize {
+        match self {
+            PollKind::YesNo => 2,
+            PollKind::MultiChoice { options, .. } => options.len(),
+        }
+    }
+
+    pub fn option_name(&self, option: usize) -> String {
+        match self {
+            PollKind::YesNo => if option == 0 { "yes" } else { "no" }.to_string(),
+            PollKind::MultiChoice { options, .. } => options[option].clone(),
+        }
+    }
+}
+
+/// How the winning option of a multi-choice poll is picked.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub enum WinningRule {
+    /// The option with the most voting power wins. There is no winner on a tie.
+    Plurality,
+    /// The option with the most voting power wins if its share of the non-abstaining voting
+    /// power exceeds the pass threshold of the poll.
+    Threshold,
+}
+
+/// How deposited tokens are turned into voting power.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum VotingMode {
+    /// One token is one vote.
+    Linear,
+    /// Voting power is the square root of the deposited tokens.
+    Quadratic,
+    /// Voting power is the deposited tokens times `1 + conviction`, where the voter picks the
+    /// conviction and its deposit stays locked for `conviction` lock periods after the poll ends.
+    Conviction,
+}
+
+impl Default for VotingMode {
+    fn default() -> Self {
+        VotingMode::Linear
+    }
+}
+
+impl VotingMode {
+    /// Returns the voting power of `tokens` voted with the given conviction.
+    pub fn weight(&self, tokens: Balance, conviction: u32) -> Balance {
+        match self {
+            VotingMode::Linear => tokens,
+            VotingMode::Quadratic => integer_sqrt(tokens),
+            VotingMode::Conviction => tokens * (1 + conviction as Balance),
+        }
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ConvictionConfig {
+    /// How long each conviction level locks the voting deposit, in nanoseconds.
+    pub lock_period: WrappedDuration,
+    pub max_conviction: u32,
+}
+
+/// How a voter spreads its voting power.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum Ballot {
+    /// All voting power on one option.
+    Option(u32),
+    /// Counted towards the quorum but not towards any option.
+    Abstain,
+    /// Voting power split across options by relative weights, as `(option, weight)` pairs.
+    Split(Vec<(u32, u32)>),
+}
+
+impl Ballot {
+    pub fn yes_no(vote: bool) -> Self {
+        Ballot::Option(if vote { 0 } else { 1 })
+    }
+
+    pub fn validate(&self, num_options: usize) -> Result<(), &'static str> {
+        match self {
+            Ballot::Option(option) => {
+                if *option as usize >= num_options {
+                    return Err("ERR_NOT_VALID_OPTION");
+                }
+            }
+            Ballot::Abstain => {}
+            Ballot::Split(weights) => {
+                if weights.is_empty() {
+                    return Err("ERR_EMPTY_SPLIT");
+                }
+                let mut seen = vec![false; num_options];
+                for (option, weight) in weights {
+                    let option = *option as usize;
+                    if option >= num_options {
+                        return Err("ERR_NOT_VALID_OPTION");
+                    }
+                    if seen[option] {
+                        return Err("ERR_DUPLICATE_OPTION");
+                    }
+                    if *weight == 0 {
+                        return Err("ERR_ZERO_WEIGHT");
+                    }
+                    seen[option] = true;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn assert_valid(&self, num_options: usize) {
+        if let Err(error) = self.validate(num_options) {
+            env::panic(error.as_bytes());
+        }
+    }
+
+    /// Splits `amount` of voting power across options. Abstaining power is not included.
+    /// Rounding leftovers go to the last option so the whole amount is always allocated.
+    pub fn allocate(&self, amount: Balance) -> Vec<(u32, Balance)> {
+        match self {
+            Ballot::Option(option) => vec![(*option, amount)],
+            Ballot::Abstain => vec![],
+            Ballot::Split(weights) => {
+                let total_weight: u64 = weights.iter().map(|(_, weight)| *weight as u64).sum();
+                let mut allocated: Balance = 0;
+                weights
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (option, weight))| {
+                        let share = if i + 1 == weights.len() {
+                            amount - allocated
+                        } else {
+                            (U256::from(amount) * U256::from(*weight) / U256::from(total_weight))
+                                .as_u128()
+                        };
+                        allocated += share;
+                        (*option, share)
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+impl Poll {
+    /// Counts the given vote in the tallies.
+    pub(crate) fn add_to_tally(&mut self, info: &AccountInfo) {
+        for (option, weight) in info.ballot.allocate(info.weight) {
+            self.option_amounts[option as usize] += weight;
+        }
+        if info.ballot == Ballot::Abstain {
+            self.abstain_amount += info.weight;
+        }
+        self.stake_amount += info.voting_tokens();
+    }
+
+    /// Takes the given vote out of the tallies.
+    pub(crate) fn remove_from_tally(&mut self, info: &AccountInfo) {
+        for (option, weight) in info.ballot.allocate(info.weight) {
+            self.option_amounts[option as usize] -= weight;
+        }
+        if info.ballot == Ballot::Abstain {
+            self.abstain_amount -= info.weight;
+        }
+        self.stake_amount -= info.voting_tokens();
+    }
+
+    /// Checks `conviction` against the voting mode of the poll and the current `conviction` of the vote.
+    pub(crate) fn validate_conviction(&self, conviction: u32, current: u32) -> Result<(), &'static str> {
+        if self.voting_mode == VotingMode::Conviction {
+            if conviction > self.config.conviction.max_conviction {
+                return Err("ERR_CONVICTION_TOO_HIGH");
+            }
+        } else if conviction != 0 {
+            return Err("ERR_CONVICTION_NOT_SUPPORTED");
+        }
+        if conviction < current {
+            return Err("ERR_CONVICTION_REDUCED");
+        }
+        Ok(())
+    }
+
+    /// Returns the voting power of the given vote according to the voting mode of the poll.
+    pub(crate) fn voting_weight(&self, info: &AccountInfo) -> Balance {
+        self.voting_mode.weight(info.voting_tokens(), info.conviction)
+    }
+
+    /// Returns until when the deposit of the given vote is locked beyond the end of the poll.
+    pub(crate) fn locked_until(&self, info: &AccountInfo) -> Option<Timestamp> {
+        if info.conviction == 0 {
+            None
+        } else {
+            Some(self.end_time + info.conviction as u64 * self.config.conviction.lock_period.0)
+        }
+    }
+
+    /// Returns the winning option according to the kind of the poll, if any.
+    pub(crate) fn find_winner(&self) -> Option<u32> {
+        let voted: Balance = self.option_amounts.iter().sum();
+        if voted == 0 {
+            return None;
+        }
+        match &self.kind {
+            PollKind::YesNo => {
+                if self.config.pass_threshold.is_exceeded_by(self.option_amounts[0], voted) {
+                    Some(0)
+                } else {
+                    None
+                }
+            }
+            PollKind::MultiChoice { winning_rule, .. } => {
+                let best_amount = *self.option_amounts.iter().max().unwrap();
+                let mut best = self.option_amounts.iter().enumerate().filter(|(_, amount)| **amount == best_amount);
+                let (best_option, _) = best.next().unwrap();
+                if best.next().is_some() {
+                    return None;
+                }
+                match winning_rule {
+                    WinningRule::Plurality => Some(best_option as u32),
+                    WinningRule::Threshold => {
+                        if self.config.pass_threshold.is_exceeded_by(best_amount, voted) {
+                            Some(best_option as u32)
+                        } else {
+                            None
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quadratic_weight() {
+        let quadratic = VotingMode::Quadratic;
+        assert_eq!(quadratic.weight(0, 0), 0);
+        assert_eq!(quadratic.weight(1, 0), 1);
+        assert_eq!(quadratic.weight(2, 0), 1);
+        assert_eq!(quadratic.weight(3, 0), 1);
+        for root in [2u128, 3, 10, 1_000_000_000_000, 10u128.pow(18), u64::MAX as u128].iter() {
+            let square = root * root;
+            assert_eq!(quadratic.weight(square, 0), *root);
+            assert_eq!(quadratic.weight(square - 1, 0), root - 1);
+            assert_eq!(quadratic.weight(square + 1, 0), *root);
+        }
+        assert_eq!(quadratic.weight(u128::MAX, 0), u64::MAX as u128);
+    }
+
+    #[test]
+    fn test_quadratic_weight_is_floor_of_square_root() {
+        for tokens in 0..10_000u128 {
+            let weight = VotingMode::Quadratic.weight(tokens, 0);
+            assert!(weight * weight <= tokens && (weight + 1) * (weight + 1) > tokens);
+        }
+    }
+
+    #[test]
+    fn test_linear_and_conviction_weights() {
+        assert_eq!(VotingMode::Linear.weight(100, 0), 100);
+        assert_eq!(VotingMode::Conviction.weight(100, 0), 100);
+        assert_eq!(VotingMode::Conviction.weight(100, 3), 400);
+    }
+}
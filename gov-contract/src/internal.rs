@@ -1,93 +1,299 @@
-use near_sdk::{Gas, Promise};
-use near_sdk::env::log;
-use near_sdk::test_utils::test_env::setup_free;
+use near_sdk::collections::UnorderedMap;
+use near_sdk::Promise;
 use crate::*;
-use crate::utils::GAS_FOR_FT_TRANSFER;
+use crate::utils::{ext_market, poll_votes_prefix, GAS_FOR_FT_TRANSFER, GAS_FOR_MARKET_OUTCOME};
 
 /// Internal Actions
 impl VotingContract {
     /// Main Method
 
     // Create poll
-    pub fn internal_create_poll(&mut self, title: String, description: String, deposit_amount: Balance, sender_id: AccountId) {
-        let mut new_poll: Poll = Poll {
+    pub fn internal_create_poll(&mut self, title: String, description: String, kind: PollKind, voting_mode: VotingMode, actions: Vec<ProposalAction>, market_parameter: Option<MarketParameter>, deposit_amount: Balance, sender_id: AccountId) {
+        let index = self.polls.len() as u32;
+        let now = env::block_timestamp();
+        let new_poll: Poll = Poll {
             creator_id: sender_id,
-            create_date: Some(U64::from(env::block_timestamp())),
-            yes_amount: 0,
-            no_amount: 0,
+            create_date: Some(U64::from(now)),
+            option_amounts: vec![0; kind.num_options()],
+            abstain_amount: 0,
             stake_amount: 0,
             status: PollStatus::InProgress,
             title,
             description,
-            votes: HashMap::new(),
-            last_epoch_height: 0,
+            kind,
+            voting_mode,
+            votes: UnorderedMap::new(poll_votes_prefix(index)),
             deposit_amount,
             total_balance_at_end_poll: 0,
+            start_time: now,
+            end_time: now + self.poll_config.voting_period.0,
+            config: self.poll_config.clone(),
+            finalized_at: None,
+            action_results: vec![ActionStatus::NotExecuted; actions.len()],
+            execution_started_at: None,
+            actions,
+            market_parameter,
+            deposit_status: DepositStatus::Locked,
+            winner: None,
         };
-        self.polls.insert(self.poll_count.into(), new_poll);
-        self.poll_count += 1;
+        self.polls.push(&new_poll);
     }
 
     // Method for validators to vote.
-    pub fn internal_vote(&mut self, index: u32, account_info: AccountInfo, account_id: AccountId) {
-        let mut cur_poll: Poll = self.polls[index];
-        let vote = account_info.vote;
-        let amount = account_info.amount;
-        let mut user_amount = amount.clone();
-
-        if account_info.vote {
-            cur_poll.yes_amount += amount;
-        } else {
-            cur_poll.no_amount += amount;
+    // Voting again adds `amount` to the previous vote and moves all of it to `ballot`.
+    // The conviction applies to the whole vote and can only be raised. `None` keeps the current one.
+    pub fn internal_vote(&mut self, index: u32, ballot: Ballot, amount: Balance, conviction: Option<u32>, account_id: AccountId) {
+        let mut cur_poll: Poll = self.internal_get_poll(index);
+        ballot.assert_valid(cur_poll.kind.num_options());
+        let mut info = cur_poll.votes.get(&account_id).unwrap_or(AccountInfo {
+            ballot: ballot.clone(),
+            amount: 0,
+            staked_power: 0,
+            weight: 0,
+            conviction: 0,
+        });
+        assert!(info.voting_tokens() + amount > 0, "ERR_NO_VOTE");
+        let conviction = conviction.unwrap_or(info.conviction);
+        if let Err(error) = cur_poll.validate_conviction(conviction, info.conviction) {
+            env::panic(error.as_bytes());
+        }
+
+        cur_poll.remove_from_tally(&info);
+        info.ballot = ballot;
+        info.amount += amount;
+        info.conviction = conviction;
+        info.weight = cur_poll.voting_weight(&info);
+        cur_poll.add_to_tally(&info);
+
+        cur_poll.votes.insert(&account_id, &info);
+        self.internal_save_poll(index, &cur_poll);
+        if info.amount > 0 {
+            self.internal_add_locked_poll(&account_id, index);
         }
+        self.internal_add_voted_poll(&account_id, index);
+    }
+
+    // Add the staked voting power of the account to its vote, casting one if needed.
+    pub(crate) fn internal_vote_with_stake(&mut self, index: u32, ballot: Ballot, staked_power: Balance, account_id: AccountId) {
+        let mut cur_poll: Poll = self.internal_get_poll(index);
+        let mut info = cur_poll.votes.get(&account_id).unwrap_or(AccountInfo {
+            ballot: ballot.clone(),
+            amount: 0,
+            staked_power: 0,
+            weight: 0,
+            conviction: 0,
+        });
+        assert_eq!(info.staked_power, 0, "ERR_STAKE_ALREADY_COUNTED");
 
-        user_amount += amount;
-        cur_poll.stake_amount = account_info.amount;
+        cur_poll.remove_from_tally(&info);
+        info.ballot = ballot;
+        info.staked_power = staked_power;
+        info.weight = cur_poll.voting_weight(&info);
+        cur_poll.add_to_tally(&info);
+
+        cur_poll.votes.insert(&account_id, &info);
+        self.internal_save_poll(index, &cur_poll);
+        self.internal_add_voted_poll(&account_id, index);
+        env::log(format!("@{} voted with stake of {} on poll {}", account_id, staked_power, index).as_bytes());
+    }
+
+    // Remove the deposit of the account from its vote and return the vote as it was.
+    // A vote with staked voting power stays counted with that power only.
+    pub(crate) fn internal_withdraw_vote(&mut self, index: u32, account_id: &AccountId) -> AccountInfo {
+        let mut cur_poll: Poll = self.internal_get_poll(index);
+        let info = cur_poll.votes.get(account_id).expect("ERR_NO_VOTE");
+        assert!(info.amount > 0, "ERR_NO_DEPOSIT");
+        self.assert_unlocked(&cur_poll, &info);
+
+        cur_poll.remove_from_tally(&info);
+        if info.staked_power > 0 {
+            let mut remaining = AccountInfo {
+                ballot: info.ballot.clone(),
+                amount: 0,
+                staked_power: info.staked_power,
+                weight: 0,
+                conviction: info.conviction,
+            };
+            remaining.weight = cur_poll.voting_weight(&remaining);
+            cur_poll.add_to_tally(&remaining);
+            cur_poll.votes.insert(account_id, &remaining);
+        } else {
+            cur_poll.votes.remove(account_id);
+        }
+        self.internal_save_poll(index, &cur_poll);
+        self.internal_remove_locked_poll(account_id, index);
 
-        cur_poll.votes.insert(account_id, AccountInfo(vote, user_amount));
-        self.check_finish(index, &mut cur_poll);
+        env::log(
+            format!("@{} withdrew vote of {} from poll {}", account_id, info.amount, index).as_bytes(),
+        );
+        info
     }
 
     /// Sub Method
-    // Ping to update the votes according to current stake of validators.
-    fn ping(&mut self, amount: Balance, index: u32) {
-        let mut cur_poll: Poll = self.polls[index];
-        let cur_epoch_height = env::epoch_height();
-
-        if cur_epoch_height != cur_poll.last_epoch_height {
-            let votes = std::mem::take(&mut cur_poll.votes);
-            cur_poll.stake_amount = 0;
-            for (account_id, account_info) in votes {
-                match account_info.vote {
-                    true => cur_poll.yes_amount += amount,
-                    false => cur_poll.no_amount += amount,
-                    _ => {
-                        env::log(
-                            format!("{} is not valid", account_id).as_bytes()
-                        );
-                    }
+    // Close the poll given the supply its quorum is measured against.
+    pub(crate) fn internal_finalize_poll(&mut self, index: u32, supply: Balance) -> PollStatus {
+        let mut cur_poll: Poll = self.internal_get_poll(index);
+        // Another finalization may have landed first.
+        if cur_poll.status != PollStatus::InProgress {
+            return cur_poll.status;
+        }
+
+        cur_poll.total_balance_at_end_poll = supply;
+        cur_poll.finalized_at = Some(env::block_timestamp());
+        let quorum = cur_poll.config.quorum.multiply(supply);
+        cur_poll.status = if cur_poll.stake_amount == 0 || cur_poll.stake_amount < quorum {
+            PollStatus::Expired
+        } else {
+            cur_poll.winner = cur_poll.find_winner();
+            if cur_poll.winner.is_some() {
+                PollStatus::Passed
+            } else {
+                PollStatus::Rejected
+            }
+        };
+        self.internal_save_poll(index, &cur_poll);
+
+        env::log(
+            format!(
+                "Poll {} finalized as {:?}. Voted {} of {} with quorum {}",
+                index, cur_poll.status, cur_poll.stake_amount, supply, quorum
+            )
+            .as_bytes(),
+        );
+        if let Some(winner) = cur_poll.winner {
+            env::log(
+                format!("Poll {} won by option {} ({})", index, winner, cur_poll.kind.option_name(winner as usize))
+                    .as_bytes(),
+            );
+        }
+        self.internal_settle_deposit(index);
+        // Passed polls are applied once executed, but the market hears about every outcome now.
+        if let Some(parameter) = cur_poll.market_parameter {
+            ext_market::on_market_poll_finalized(
+                index,
+                parameter,
+                cur_poll.status.clone(),
+                &self.market_id,
+                NO_DEPOSIT,
+                GAS_FOR_MARKET_OUTCOME,
+            );
+        }
+        cur_poll.status
+    }
+
+    // Refund the proposal deposit to the creator if the poll reached quorum, otherwise forfeit it.
+    // Passed polls are settled once their execution timelock ends, as they can be vetoed until then.
+    pub(crate) fn internal_settle_deposit(&mut self, index: u32) {
+        let cur_poll: Poll = self.internal_get_poll(index);
+        match cur_poll.status {
+            PollStatus::Passed => {
+                if !self.is_in_timelock(&cur_poll) {
+                    self.internal_transfer_deposit(index, cur_poll, None, DepositStatus::Refunded);
                 }
-                cur_poll.stake_amount += amount;
-                cur_poll.votes
-                    .insert(account_id, AccountInfo(account_info.vote, amount));
             }
-            cur_poll.last_epoch_height = cur_epoch_height;
-            self.check_finish(index, &mut cur_poll);
+            PollStatus::Rejected | PollStatus::Cancelled | PollStatus::ExecutionFailed => {
+                self.internal_transfer_deposit(index, cur_poll, None, DepositStatus::Refunded);
+            }
+            PollStatus::Expired => self.internal_forfeit_deposit(index),
+            PollStatus::Vetoed => match cur_poll.config.veto_deposit_policy {
+                VetoDepositPolicy::Refund => {
+                    self.internal_transfer_deposit(index, cur_poll, None, DepositStatus::Refunded);
+                }
+                VetoDepositPolicy::Forfeit => self.internal_forfeit_deposit(index),
+            },
+            PollStatus::InProgress => {}
         }
     }
 
-    // Check whether the voting has ended.
-    fn check_finish(&mut self, index: u32, cur_poll: &mut Poll) {
-        if cur_poll.stake_amount > 2 * cur_poll.stake_amount / 3 {
-            /// TODO: 끝내는 조건 필요
-            if cur_poll.yes_amount > cur_poll.no_amount {
-                cur_poll.status = PollStatus::Passed;
-            } else {
-                cur_poll.status = PollStatus::Rejected;
+    // Burn the proposal deposit or send it to the treasury, following the poll's policy.
+    pub(crate) fn internal_forfeit_deposit(&mut self, index: u32) {
+        let mut cur_poll: Poll = self.internal_get_poll(index);
+        match cur_poll.config.failed_deposit_policy.clone() {
+            FailedDepositPolicy::Burn => {
+                self.burned_amount += cur_poll.deposit_amount;
+                cur_poll.deposit_status = DepositStatus::Forfeited;
+                self.internal_save_poll(index, &cur_poll);
+                env::log(format!("Poll {} deposit {} burned", index, cur_poll.deposit_amount).as_bytes());
+            }
+            FailedDepositPolicy::Treasury { treasury_id } => {
+                self.internal_transfer_deposit(index, cur_poll, Some(treasury_id), DepositStatus::Forfeited);
             }
         }
+    }
+
+    // Send the proposal deposit to `receiver_id`, or to the creator if `None`.
+    fn internal_transfer_deposit(
+        &mut self,
+        index: u32,
+        mut cur_poll: Poll,
+        receiver_id: Option<AccountId>,
+        settled_status: DepositStatus,
+    ) {
+        if cur_poll.deposit_amount == 0 {
+            cur_poll.deposit_status = settled_status;
+            self.internal_save_poll(index, &cur_poll);
+            return;
+        }
+        let receiver_id = receiver_id.unwrap_or_else(|| cur_poll.creator_id.clone());
+        cur_poll.deposit_status = DepositStatus::Transferring;
+        self.internal_save_poll(index, &cur_poll);
+
+        env::log(
+            format!("Poll {} deposit {} sent to @{}", index, cur_poll.deposit_amount, receiver_id).as_bytes(),
+        );
+        self.internal_send_tokens(&receiver_id, &self.token_id, cur_poll.deposit_amount)
+            .then(ext_self::on_deposit_transferred(
+                index,
+                settled_status,
+                &env::current_account_id(),
+                NO_DEPOSIT,
+                GAS_FOR_RESOLVE_TRANSFER,
+            ));
+    }
+
+    /// Storage
+    pub(crate) fn internal_get_locked_polls(&self, account_id: &AccountId) -> Vec<u32> {
+        self.account_locked_polls.get(account_id).unwrap_or_default()
+    }
+
+    pub(crate) fn internal_add_locked_poll(&mut self, account_id: &AccountId, index: u32) {
+        let mut locked_polls = self.internal_get_locked_polls(account_id);
+        if !locked_polls.contains(&index) {
+            locked_polls.push(index);
+            self.account_locked_polls.insert(account_id, &locked_polls);
+        }
+    }
+
+    pub(crate) fn internal_add_voted_poll(&mut self, account_id: &AccountId, index: u32) {
+        let mut voted_polls = self.account_voted_polls.get(account_id).unwrap_or_default();
+        if !voted_polls.contains(&index) {
+            voted_polls.push(index);
+            self.account_voted_polls.insert(account_id, &voted_polls);
+        }
+    }
+
+    // Returns false if the account has nothing locked in the poll.
+    pub(crate) fn internal_remove_locked_poll(&mut self, account_id: &AccountId, index: u32) -> bool {
+        let mut locked_polls = self.internal_get_locked_polls(account_id);
+        let len = locked_polls.len();
+        locked_polls.retain(|locked| *locked != index);
+        if locked_polls.len() == len {
+            return false;
+        }
+        if locked_polls.is_empty() {
+            self.account_locked_polls.remove(account_id);
+        } else {
+            self.account_locked_polls.insert(account_id, &locked_polls);
+        }
+        true
+    }
+
+    pub(crate) fn internal_get_poll(&self, index: u32) -> Poll {
+        self.polls.get(index as u64).expect("ERR_NOT_VALID_POLL_INDEX")
+    }
 
-        self.polls.insert(index.into(), cur_poll.into());
+    pub(crate) fn internal_save_poll(&mut self, index: u32, poll: &Poll) {
+        self.polls.replace(index as u64, poll);
     }
 
     /// Called
@@ -106,4 +312,4 @@ impl VotingContract {
             GAS_FOR_FT_TRANSFER,
         )
     }
-}
\ No newline at end of file
+}
@@ -1,52 +1,254 @@
+mod ballot;
+mod market;
 mod view;
 mod token_receiver;
 mod internal;
 mod utils;
 mod owner;
+mod proposal;
 
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::json_types::{U128, U64, WrappedTimestamp};
-use near_sdk::{env, near_bindgen, AccountId, Balance, EpochHeight};
-use std::collections::HashMap;
+use near_sdk::collections::{LookupMap, UnorderedMap, Vector};
+use near_sdk::json_types::{U128, U64, WrappedDuration, WrappedTimestamp};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{
+    assert_one_yocto, env, ext_contract, near_bindgen, AccountId, Balance, Promise, Timestamp,
+};
+
+use crate::ballot::{Ballot, ConvictionConfig, PollKind, VotingMode};
+use crate::market::MarketParameter;
+use crate::proposal::{ActionStatus, ProposalAction};
+use crate::utils::{
+    assert_reason, ext_fungible_token, ext_self, ext_stake_pool, ext_staking, log_poll_closed, U256, GAS_FOR_GET_SUPPLY,
+    GAS_FOR_GET_VOTING_POWER, GAS_FOR_LOCK_POOL_STAKE, GAS_FOR_RESOLVE_FINALIZE, GAS_FOR_RESOLVE_STAKING_LOCK,
+    GAS_FOR_RESOLVE_TRANSFER, GAS_FOR_RESOLVE_VOTING_POWER, NO_DEPOSIT,
+};
 
 #[global_allocator]
 static ALLOC: near_sdk::wee_alloc::WeeAlloc = near_sdk::wee_alloc::WeeAlloc::INIT;
 
 #[derive(BorshDeserialize, BorshSerialize, Debug, PartialEq)]
 pub struct AccountInfo {
-    id: AccountId,
-    vote: bool,
+    ballot: Ballot,
+    /// Voting tokens deposited.
     amount: Balance,
+    /// Voting power queried from the stake sources, counted once per poll.
+    staked_power: Balance,
+    /// Voting power of `amount` according to the voting mode of the poll.
+    weight: Balance,
+    /// Number of lock periods the deposit stays locked after the poll ends, in conviction mode.
+    conviction: u32,
 }
 
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize)]
 pub struct VotingContract {
     owner: AccountId,
-    polls: Vec<Poll>,
-    poll_count: u32,
-    pause: bool,
+    /// Polls indexed by their id.
+    polls: Vector<Poll>,
+    paused: bool,
     market_id: AccountId,
     token_id: AccountId,
     min_create_poll_amount: Balance,
+    /// Voting rules applied to newly created polls.
+    poll_config: PollConfig,
+    /// Polls in which each account has voting tokens locked.
+    account_locked_polls: LookupMap<AccountId, Vec<u32>>,
+    /// Proposal deposits taken from spam polls.
+    slashed_amount: Balance,
+    /// Forfeited proposal deposits that are locked in this contract for good.
+    burned_amount: Balance,
+    /// Contracts whose staked positions count as voting power.
+    stake_sources: Vec<StakeSource>,
+    /// Polls each account has voted on.
+    account_voted_polls: LookupMap<AccountId, Vec<u32>>,
+    /// Can veto passed polls during their execution timelock.
+    guardian: Option<AccountId>,
 }
 
-#[near_bindgen]
-#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, PartialEq)]
-#[serde(crate = "near_sdk::serde")]
+#[derive(BorshDeserialize, BorshSerialize)]
 pub struct Poll {
     creator_id: AccountId,
     status: PollStatus,
     create_date: Option<WrappedTimestamp>,
     title: String,
     description: String,
+    kind: PollKind,
+    voting_mode: VotingMode,
     deposit_amount: Balance,
-    votes: HashMap<AccountId, AccountInfo>,
-    yes_amount: Balance,
-    no_amount: Balance,
+    /// Votes of this poll, stored under a per-poll prefix.
+    votes: UnorderedMap<AccountId, AccountInfo>,
+    /// Voting power on each option of `kind`.
+    option_amounts: Vec<Balance>,
+    /// Voting power that abstained. It counts towards the quorum only.
+    abstain_amount: Balance,
+    /// Voting tokens deposited by all votes, abstaining included. The quorum is measured on it.
     stake_amount: Balance,
     total_balance_at_end_poll: Balance,
-    last_epoch_height: EpochHeight,
+    /// Voting is open from `start_time` (inclusive) until `end_time` (exclusive).
+    start_time: Timestamp,
+    end_time: Timestamp,
+    /// Voting rules at the time the poll was created.
+    config: PollConfig,
+    /// When `finalize_poll` closed the poll.
+    finalized_at: Option<Timestamp>,
+    /// Function calls to make once the poll has passed.
+    actions: Vec<ProposalAction>,
+    /// Market change voted on, for polls opened by the market.
+    market_parameter: Option<MarketParameter>,
+    /// Result of each of `actions`.
+    action_results: Vec<ActionStatus>,
+    /// When `execute` last scheduled the actions.
+    execution_started_at: Option<Timestamp>,
+    deposit_status: DepositStatus,
+    /// Winning option, set when the poll passes.
+    winner: Option<u32>,
+}
+
+/// Where the proposal deposit of a poll is in its lifecycle.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum DepositStatus {
+    /// Held by this contract until the poll is settled.
+    Locked,
+    /// A transfer of the deposit is in flight.
+    Transferring,
+    /// Returned to the creator because the poll reached quorum, was cancelled or was vetoed
+    /// under the refund policy.
+    Refunded,
+    /// Burned or sent to the treasury because the poll missed quorum, was slashed or was vetoed
+    /// under the forfeit policy.
+    Forfeited,
+}
+
+/// What happens to the proposal deposit of a poll that misses quorum.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub enum FailedDepositPolicy {
+    /// Keep the deposit locked in this contract for good.
+    Burn,
+    /// Send the deposit to the community treasury.
+    Treasury { treasury_id: AccountId },
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Fraction {
+    pub numerator: u32,
+    pub denominator: u32,
+}
+
+impl Fraction {
+    pub fn assert_valid(&self) {
+        assert_ne!(self.denominator, 0, "Denominator must be a positive number");
+        assert!(
+            self.numerator <= self.denominator,
+            "The fraction must be less or equal to 1"
+        );
+    }
+
+    pub fn multiply(&self, value: Balance) -> Balance {
+        (U256::from(self.numerator) * U256::from(value) / U256::from(self.denominator)).as_u128()
+    }
+
+    /// Returns true if `part / total` is strictly greater than this fraction.
+    pub fn is_exceeded_by(&self, part: Balance, total: Balance) -> bool {
+        U256::from(part) * U256::from(self.denominator) > U256::from(total) * U256::from(self.numerator)
+    }
+}
+
+/// The supply the quorum of a poll is measured against.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub enum QuorumBasis {
+    /// Total supply of `token_id`.
+    TotalSupply,
+    /// Amount of Synchro locked in the given staking contract.
+    StakedSupply { staking_id: AccountId },
+}
+
+/// What happens to the proposal deposit of a vetoed poll.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub enum VetoDepositPolicy {
+    /// Return the deposit to the creator.
+    Refund,
+    /// Treat the deposit like the one of a poll that missed quorum.
+    Forfeit,
+}
+
+/// A contract whose staked positions count as voting power.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub enum StakeSource {
+    /// Synchro staking contract. Its shares are valued in locked Synchro.
+    /// This contract has to be its vote locker.
+    SynchroStaking { contract_id: AccountId },
+    /// Pair stake pool. The staked balance is counted, the receipts held by the voter included.
+    /// This contract has to be its vote locker, and the pool the vote locker of its liquid token.
+    /// Only pools staking the voting token are accepted, as their balance is counted as is.
+    PairStakePool { contract_id: AccountId },
+}
+
+/// Same JSON as the `Asset` a pair stake pool stakes.
+#[derive(Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub enum PoolAsset {
+    Token { token_id: AccountId },
+    Native,
+}
+
+impl Poll {
+    /// Returns until when stake voted on this poll is locked in the stake sources. It covers the
+    /// longest conviction lock, as the conviction of a vote can be raised after it counted stake.
+    pub(crate) fn stake_unlock_time(&self) -> Timestamp {
+        if self.voting_mode == VotingMode::Conviction {
+            let conviction = &self.config.conviction;
+            self.end_time + conviction.max_conviction as u64 * conviction.lock_period.0
+        } else {
+            self.end_time
+        }
+    }
+
+    /// Returns when the execution timelock of a finalized poll ends.
+    pub(crate) fn timelock_ends_at(&self) -> Option<Timestamp> {
+        self.finalized_at.map(|finalized_at| finalized_at + self.config.execution_delay.0)
+    }
+}
+
+impl AccountInfo {
+    /// Voting tokens behind this vote, deposited or staked.
+    pub(crate) fn voting_tokens(&self) -> Balance {
+        self.amount + self.staked_power
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PollConfig {
+    /// How long a poll accepts votes, in nanoseconds.
+    pub voting_period: WrappedDuration,
+    /// Share of the quorum basis that has to vote for a poll to be valid.
+    pub quorum: Fraction,
+    pub quorum_basis: QuorumBasis,
+    /// Share of the non-abstaining votes the winning option has to exceed for a poll to pass.
+    /// Plurality multi-choice polls ignore it.
+    pub pass_threshold: Fraction,
+    /// How long after finalization the actions of a passed poll can be executed, in nanoseconds.
+    pub execution_delay: WrappedDuration,
+    pub failed_deposit_policy: FailedDepositPolicy,
+    /// Lock rules of polls in conviction voting mode.
+    pub conviction: ConvictionConfig,
+    pub veto_deposit_policy: VetoDepositPolicy,
+}
+
+impl PollConfig {
+    pub fn assert_valid(&self) {
+        assert!(self.voting_period.0 > 0, "ERR_ZERO_VOTING_PERIOD");
+        self.quorum.assert_valid();
+        self.pass_threshold.assert_valid();
+        assert!(self.conviction.lock_period.0 > 0, "ERR_ZERO_LOCK_PERIOD");
+    }
 }
 
 impl Default for VotingContract {
@@ -58,52 +260,291 @@ impl Default for VotingContract {
 #[near_bindgen]
 impl VotingContract {
     #[init]
-    pub fn new(token_id:AccountId, market_id: AccountId, min_create_poll_amount: Balance) -> Self {
+    pub fn new(token_id:AccountId, market_id: AccountId, min_create_poll_amount: Balance, poll_config: PollConfig) -> Self {
         assert!(!env::state_exists(), "The contract is already initialized");
+        poll_config.assert_valid();
         VotingContract {
             owner: env::predecessor_account_id(),
-            polls: Vec::new(),
-            poll_count: 0,
-            pause: false,
+            polls: Vector::new(b"p".to_vec()),
+            paused: false,
             market_id,
             token_id,
             min_create_poll_amount,
+            poll_config,
+            account_locked_polls: LookupMap::new(b"a".to_vec()),
+            slashed_amount: 0,
+            burned_amount: 0,
+            stake_sources: vec![],
+            account_voted_polls: LookupMap::new(b"w".to_vec()),
+            guardian: None,
         }
     }
 
-    // claim user
-    pub fn claim(&self, index: u32) {
+    /// Returns the voting tokens of the predecessor locked in a poll that is no longer in progress.
+    /// Requires attached deposit of exactly 1 yoctoNEAR.
+    #[payable]
+    pub fn withdraw_vote_deposit(&mut self, index: u32) -> Promise {
+        assert_one_yocto();
         self.assert_contract_running();
         self.assert_index(index);
-        self.check_status(index);
 
         let account_id = env::predecessor_account_id();
-        let mut cur_poll: Poll = self.polls[index];
-        let votes = std::mem::take(&mut cur_poll.votes);
-        if votes.contains_key(&env::predecessor_account_id()) {
-            let account = votes.get(&account_id).unwrap();
-            let amount = (U256::from(cur_poll.stake_amount) * U256::from(account.amount) / U256::from(cur_poll.deposit_amount)).as_u128();
+        let cur_poll: Poll = self.internal_get_poll(index);
+        assert_ne!(cur_poll.status, PollStatus::InProgress, "ERR_POLL_IN_PROGRESS");
+        assert!(self.internal_remove_locked_poll(&account_id, index), "ERR_NO_LOCKED_DEPOSIT");
+        let info = cur_poll.votes.get(&account_id).expect("ERR_NO_VOTE");
+        self.assert_unlocked(&cur_poll, &info);
+        let amount = info.amount;
 
-            self.internal_send_tokens(&self.owner, &self.token_id, amount);
-        } else {
-            env::panic(b"ERR: non-whitelisted token can NOT deposit into lost-found.");
+        env::log(format!("@{} withdrawing {} from poll {}", account_id, amount, index).as_bytes());
+        self.internal_send_tokens(&account_id, &self.token_id, amount)
+            .then(ext_self::on_vote_deposit_withdrawn(
+                index,
+                account_id,
+                &env::current_account_id(),
+                NO_DEPOSIT,
+                GAS_FOR_RESOLVE_TRANSFER,
+            ))
+    }
+
+    /// Switches the vote of the predecessor on an active poll to `ballot`, keeping its amount and conviction.
+    pub fn change_vote(&mut self, index: u32, ballot: Ballot) {
+        self.assert_contract_running();
+        self.assert_index(index);
+        self.assert_status(index);
+        self.assert_voting_period(index);
+
+        self.internal_vote(index, ballot, 0, None, env::predecessor_account_id());
+    }
+
+    /// Votes on an active poll with the stake the predecessor has in the stake sources, without
+    /// transferring tokens. The stake sources lock the stake until the poll can't count it
+    /// anymore, so it can't be moved to another account and voted again. The locked stake is
+    /// cached in the vote and counted once per poll.
+    pub fn vote_with_stake(&mut self, index: u32, ballot: Ballot) -> Promise {
+        self.assert_contract_running();
+        self.assert_index(index);
+        self.assert_status(index);
+        self.assert_voting_period(index);
+        assert!(!self.stake_sources.is_empty(), "ERR_NO_STAKE_SOURCES");
+
+        let account_id = env::predecessor_account_id();
+        let cur_poll: Poll = self.internal_get_poll(index);
+        ballot.assert_valid(cur_poll.kind.num_options());
+        let counted = cur_poll.votes.get(&account_id).map_or(0, |info| info.staked_power);
+        assert_eq!(counted, 0, "ERR_STAKE_ALREADY_COUNTED");
+        let unlock_time = U64(cur_poll.stake_unlock_time());
+
+        let mut query: Option<Promise> = None;
+        for source in self.stake_sources.iter() {
+            let source_query = match source {
+                // The shares are locked, then valued in locked Synchro.
+                StakeSource::SynchroStaking { contract_id } => ext_staking::lock_for_vote(
+                    account_id.clone(),
+                    unlock_time,
+                    contract_id,
+                    NO_DEPOSIT,
+                    GAS_FOR_GET_VOTING_POWER,
+                )
+                .then(ext_self::on_staking_shares_locked(
+                    account_id.clone(),
+                    contract_id.clone(),
+                    &env::current_account_id(),
+                    NO_DEPOSIT,
+                    GAS_FOR_RESOLVE_STAKING_LOCK,
+                )),
+                // The pool locks the receipts at its liquid token before valuing them.
+                StakeSource::PairStakePool { contract_id } => ext_stake_pool::lock_for_vote(
+                    account_id.clone(),
+                    unlock_time,
+                    contract_id,
+                    NO_DEPOSIT,
+                    GAS_FOR_LOCK_POOL_STAKE,
+                ),
+            };
+            query = Some(match query {
+                Some(query) => query.and(source_query),
+                None => source_query,
+            });
+        }
+        query.unwrap().then(ext_self::on_voting_power(
+            index,
+            account_id,
+            ballot,
+            &env::current_account_id(),
+            NO_DEPOSIT,
+            GAS_FOR_RESOLVE_VOTING_POWER,
+        ))
+    }
+
+    /// Withdraws the vote of the predecessor from an active poll and returns the voted tokens.
+    /// Staked voting power stays counted. Votes with a conviction are locked and can't be withdrawn.
+    /// Requires attached deposit of exactly 1 yoctoNEAR.
+    #[payable]
+    pub fn withdraw_vote(&mut self, index: u32) -> Promise {
+        assert_one_yocto();
+        self.assert_contract_running();
+        self.assert_index(index);
+        self.assert_status(index);
+        self.assert_voting_period(index);
+
+        let account_id = env::predecessor_account_id();
+        let info = self.internal_withdraw_vote(index, &account_id);
+
+        self.internal_send_tokens(&account_id, &self.token_id, info.amount)
+            .then(ext_self::on_vote_withdrawn(
+                index,
+                account_id,
+                info.ballot,
+                info.amount.into(),
+                &env::current_account_id(),
+                NO_DEPOSIT,
+                GAS_FOR_RESOLVE_TRANSFER,
+            ))
+    }
+
+    /// Closes the poll once its voting period has ended. Anyone can call it.
+    /// The poll is `Expired` if the votes don't reach the quorum, otherwise `Passed` if an option
+    /// wins according to the poll kind and `Rejected` if none does. The supply the quorum is
+    /// measured against is queried cross-contract, so the status is set in `on_finalize_poll`.
+    pub fn finalize_poll(&mut self, index: u32) -> Promise {
+        self.assert_index(index);
+        let cur_poll: Poll = self.internal_get_poll(index);
+        assert_eq!(cur_poll.status, PollStatus::InProgress, "ERR_POLL_NOT_IN_PROGRESS");
+        assert!(env::block_timestamp() >= cur_poll.end_time, "ERR_VOTING_PERIOD_NOT_ENDED");
+
+        let supply = match &cur_poll.config.quorum_basis {
+            QuorumBasis::TotalSupply => ext_fungible_token::ft_total_supply(
+                &self.token_id,
+                NO_DEPOSIT,
+                GAS_FOR_GET_SUPPLY,
+            ),
+            QuorumBasis::StakedSupply { staking_id } => ext_staking::get_locked_token_amount(
+                staking_id,
+                NO_DEPOSIT,
+                GAS_FOR_GET_SUPPLY,
+            ),
         };
+        supply.then(ext_self::on_finalize_poll(
+            index,
+            &env::current_account_id(),
+            NO_DEPOSIT,
+            GAS_FOR_RESOLVE_FINALIZE,
+        ))
     }
 
-    pub fn stop_vote(&mut self, index: u32) {
+    /// Refunds or forfeits the proposal deposit of a poll that is no longer in progress.
+    /// Anyone can call it, e.g. to retry after a failed transfer. Finalization already does this,
+    /// except for passed polls whose deposit stays locked until their execution timelock ends,
+    /// since they can still be vetoed.
+    pub fn settle_poll_deposit(&mut self, index: u32) {
+        self.assert_index(index);
+        let cur_poll: Poll = self.internal_get_poll(index);
+        assert_ne!(cur_poll.status, PollStatus::InProgress, "ERR_POLL_IN_PROGRESS");
+        assert_eq!(cur_poll.deposit_status, DepositStatus::Locked, "ERR_DEPOSIT_SETTLED");
+        if cur_poll.status == PollStatus::Passed {
+            assert!(!self.is_in_timelock(&cur_poll), "ERR_EXECUTION_TIMELOCK");
+        }
+
+        self.internal_settle_deposit(index);
+    }
+
+    /// Owner's method.
+    /// Closes a spam poll in progress and forfeits its proposal deposit, or forfeits the deposit
+    /// of a rejected one. Voters can still withdraw their deposits.
+    pub fn slash_poll(&mut self, index: u32) {
+        self.assert_owner();
+        self.assert_index(index);
+
+        let mut cur_poll: Poll = self.internal_get_poll(index);
+        assert!(
+            cur_poll.status == PollStatus::InProgress || cur_poll.status == PollStatus::Rejected,
+            "ERR_POLL_NOT_SLASHABLE"
+        );
+        assert_eq!(cur_poll.deposit_status, DepositStatus::Locked, "ERR_DEPOSIT_SETTLED");
+        if cur_poll.status == PollStatus::InProgress {
+            cur_poll.status = PollStatus::Expired;
+        }
+        self.slashed_amount += cur_poll.deposit_amount;
+        env::log(format!("Poll {} slashed, deposit {} taken", index, cur_poll.deposit_amount).as_bytes());
+        match cur_poll.config.failed_deposit_policy.clone() {
+            // The deposit is counted as slashed, not as burned as well.
+            FailedDepositPolicy::Burn => {
+                cur_poll.deposit_status = DepositStatus::Forfeited;
+                self.internal_save_poll(index, &cur_poll);
+            }
+            FailedDepositPolicy::Treasury { .. } => {
+                self.internal_save_poll(index, &cur_poll);
+                self.internal_forfeit_deposit(index);
+            }
+        }
+    }
+
+    /// Owner's method.
+    /// Marks a poll in progress `Expired`. The reason is logged. Passed polls can only be stopped
+    /// by the guardian veto.
+    pub fn stop_vote(&mut self, index: u32, reason: String) {
         self.assert_owner();
+        self.assert_index(index);
+        assert_reason(&reason);
 
-        let mut cur_poll: Poll = self.polls[index];
+        let mut cur_poll: Poll = self.internal_get_poll(index);
+        assert_eq!(cur_poll.status, PollStatus::InProgress, "ERR_POLL_NOT_IN_PROGRESS");
         cur_poll.status = PollStatus::Expired;
-        self.polls.insert(index.into(), cur_poll);
+        self.internal_save_poll(index, &cur_poll);
+
+        log_poll_closed(index, &cur_poll.status, &self.owner, &reason);
+    }
+
+    /// Guardian's method.
+    /// Vetoes a passed poll during its execution timelock, so its actions can't be executed.
+    /// The proposal deposit is settled following the veto deposit policy of the poll.
+    pub fn veto_poll(&mut self, index: u32, reason: String) {
+        let guardian = self.guardian.clone().expect("ERR_NO_GUARDIAN");
+        assert_eq!(env::predecessor_account_id(), guardian, "ERR_NOT_ALLOWED_GUARDIAN");
+        self.assert_index(index);
+        assert_reason(&reason);
+
+        let mut cur_poll: Poll = self.internal_get_poll(index);
+        assert_eq!(cur_poll.status, PollStatus::Passed, "ERR_POLL_NOT_PASSED");
+        assert!(self.is_in_timelock(&cur_poll), "ERR_TIMELOCK_ENDED");
+        cur_poll.status = PollStatus::Vetoed;
+        self.internal_save_poll(index, &cur_poll);
+
+        log_poll_closed(index, &cur_poll.status, &guardian, &reason);
+        if cur_poll.deposit_status == DepositStatus::Locked {
+            self.internal_settle_deposit(index);
+        }
+    }
+
+    /// Lets the creator withdraw a poll nobody has voted on yet. The deposit is refunded.
+    pub fn cancel_poll(&mut self, index: u32, reason: String) {
+        self.assert_index(index);
+        assert_reason(&reason);
+
+        let mut cur_poll: Poll = self.internal_get_poll(index);
+        assert_eq!(env::predecessor_account_id(), cur_poll.creator_id, "ERR_NOT_POLL_CREATOR");
+        assert_eq!(cur_poll.status, PollStatus::InProgress, "ERR_POLL_NOT_IN_PROGRESS");
+        assert!(cur_poll.votes.is_empty(), "ERR_POLL_HAS_VOTES");
+        cur_poll.status = PollStatus::Cancelled;
+        self.internal_save_poll(index, &cur_poll);
+
+        log_poll_closed(index, &cur_poll.status, &cur_poll.creator_id, &reason);
+        self.internal_settle_deposit(index);
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(crate = "near_sdk::serde")]
 pub enum PollStatus {
     InProgress,
     Passed,
     Rejected,
     Expired, // Depricated
+    /// Passed, then vetoed by the guardian during the execution timelock.
+    Vetoed,
+    /// Withdrawn by its creator before anyone voted.
+    Cancelled,
+    /// Passed, but its actions couldn't all be executed and the owner or guardian gave up on them.
+    ExecutionFailed,
 }
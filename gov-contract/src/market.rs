@@ -0,0 +1,54 @@
+use near_sdk::json_types::Base64VecU8;
+use near_sdk::serde_json::json;
+use crate::*;
+use crate::utils::{GAS_FOR_MARKET_CALLBACK, MAX_DESCRIPTION_LENGTH, MAX_TITLE_LENGTH};
+
+/// Market method called with the outcome of a passed market parameter poll.
+pub const MARKET_CALLBACK_METHOD: &str = "on_market_poll_passed";
+
+/// A change of the market that needs the approval of the voters.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum MarketParameter {
+    ListPair { pair_id: String },
+    DelistPair { pair_id: String },
+}
+
+#[near_bindgen]
+impl VotingContract {
+    /// Market's method.
+    /// Opens a yes/no poll on a market parameter without a proposal deposit and returns its index.
+    /// Once the poll has passed, executing it calls `on_market_poll_passed(index, parameter)` on the
+    /// market, so the change goes through the same execution timelock and guardian veto as any
+    /// other proposal. Whether it passed or not, the market is told the outcome of the poll through
+    /// `on_market_poll_finalized(index, parameter, status)` once it is finalized.
+    pub fn create_market_poll(&mut self, title: String, description: String, parameter: MarketParameter) -> u32 {
+        self.assert_contract_running();
+        self.assert_market(env::predecessor_account_id());
+        assert!(!title.trim().is_empty(), "ERR_EMPTY_TITLE");
+        assert!(title.len() <= MAX_TITLE_LENGTH, "ERR_TITLE_TOO_LONG");
+        assert!(description.len() <= MAX_DESCRIPTION_LENGTH, "ERR_DESCRIPTION_TOO_LONG");
+
+        let index = self.polls.len() as u32;
+        let args = json!({ "index": index, "parameter": parameter }).to_string().into_bytes();
+        let action = ProposalAction {
+            receiver_id: self.market_id.clone(),
+            method_name: MARKET_CALLBACK_METHOD.to_string(),
+            args: Base64VecU8::from(args),
+            deposit: U128(0),
+            gas: U64(GAS_FOR_MARKET_CALLBACK),
+        };
+        env::log(format!("Market @{} opened poll {} on {:?}", self.market_id, index, parameter).as_bytes());
+        self.internal_create_poll(
+            title,
+            description,
+            PollKind::YesNo,
+            VotingMode::Linear,
+            vec![action],
+            Some(parameter),
+            0,
+            self.market_id.clone(),
+        );
+        index
+    }
+}
@@ -1,5 +1,7 @@
 use near_sdk::json_types::ValidAccountId;
+use near_sdk::PromiseOrValue;
 use crate::*;
+use crate::utils::{GAS_FOR_GET_POOL_ASSET, GAS_FOR_RESOLVE_STAKE_SOURCES};
 
 #[near_bindgen]
 impl VotingContract {
@@ -42,4 +44,48 @@ impl VotingContract {
 
         self.min_create_poll_amount = amount;
     }
+
+    pub fn set_poll_config(&mut self, poll_config: PollConfig) {
+        self.assert_owner();
+        poll_config.assert_valid();
+
+        self.poll_config = poll_config;
+    }
+
+    pub fn set_guardian(&mut self, guardian: Option<ValidAccountId>) {
+        self.assert_owner();
+
+        self.guardian = guardian.map(|guardian| guardian.into());
+    }
+
+    /// The staked balance of a pair stake pool is added to voting power as is, so the sources are
+    /// only set once each pool answers that it stakes the voting token.
+    pub fn set_stake_sources(&mut self, stake_sources: Vec<StakeSource>) -> PromiseOrValue<()> {
+        self.assert_owner();
+
+        let mut query: Option<Promise> = None;
+        for source in stake_sources.iter() {
+            if let StakeSource::PairStakePool { contract_id } = source {
+                let asset_query = ext_stake_pool::get_asset(contract_id, NO_DEPOSIT, GAS_FOR_GET_POOL_ASSET);
+                query = Some(match query {
+                    Some(query) => query.and(asset_query),
+                    None => asset_query,
+                });
+            }
+        }
+        match query {
+            Some(query) => query
+                .then(ext_self::on_stake_sources_checked(
+                    stake_sources,
+                    &env::current_account_id(),
+                    NO_DEPOSIT,
+                    GAS_FOR_RESOLVE_STAKE_SOURCES,
+                ))
+                .into(),
+            None => {
+                self.stake_sources = stake_sources;
+                PromiseOrValue::Value(())
+            }
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,195 @@
+use near_sdk::json_types::Base64VecU8;
+use near_sdk::{Gas, PromiseResult};
+use crate::*;
+use crate::utils::{EXECUTION_TIMEOUT, GAS_FOR_RESOLVE_ACTION};
+
+/// A function call made by this contract once the poll carrying it has passed.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ProposalAction {
+    pub receiver_id: AccountId,
+    pub method_name: String,
+    pub args: Base64VecU8,
+    pub deposit: U128,
+    pub gas: U64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum ActionStatus {
+    NotExecuted,
+    /// The function call is scheduled and its result is not known yet.
+    Executing,
+    Succeeded,
+    Failed,
+}
+
+/// Represents a proposal action and its result readable by humans.
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct HumanReadableAction {
+    #[serde(flatten)]
+    pub action: ProposalAction,
+    pub status: ActionStatus,
+}
+
+#[near_bindgen]
+impl VotingContract {
+    /// Runs the actions of a passed poll once its execution timelock has elapsed. Anyone can call it.
+    /// Actions run one after another in the listed order and only the ones that haven't succeeded
+    /// yet are scheduled, so calling it again retries the failed actions. Actions whose result is
+    /// still unknown `EXECUTION_TIMEOUT` after they were scheduled are retried as well.
+    /// Actions can't call the voting token, which holds the deposits of the voters, nor this
+    /// contract, whose private callbacks would accept them.
+    pub fn execute(&mut self, index: u32) -> Promise {
+        self.assert_contract_running();
+        self.assert_index(index);
+        let mut cur_poll: Poll = self.internal_get_poll(index);
+        assert_eq!(cur_poll.status, PollStatus::Passed, "ERR_POLL_NOT_PASSED");
+        let timelock_ends_at = cur_poll.timelock_ends_at().expect("ERR_POLL_NOT_FINALIZED");
+        let now = env::block_timestamp();
+        assert!(now >= timelock_ends_at, "ERR_EXECUTION_TIMELOCK");
+        assert!(!cur_poll.is_executing(now), "ERR_ACTIONS_ALREADY_EXECUTING");
+
+        let pending: Vec<usize> = (0..cur_poll.actions.len())
+            .filter(|&i| cur_poll.action_results[i] != ActionStatus::Succeeded)
+            .collect();
+        assert!(!pending.is_empty(), "ERR_NOTHING_TO_EXECUTE");
+        assert!(
+            pending.iter().all(|&i| cur_poll.actions[i].receiver_id != self.token_id),
+            "ERR_ACTION_TARGETS_VOTING_TOKEN"
+        );
+        assert!(
+            pending.iter().all(|&i| cur_poll.actions[i].receiver_id != env::current_account_id()),
+            "ERR_ACTION_TARGETS_VOTING_CONTRACT"
+        );
+        let required_gas: Gas = pending
+            .iter()
+            .map(|&i| cur_poll.actions[i].gas.0 + GAS_FOR_RESOLVE_ACTION)
+            .sum();
+        assert!(env::prepaid_gas() > required_gas, "ERR_NOT_ENOUGH_GAS");
+
+        let mut promise: Option<Promise> = None;
+        for i in pending {
+            let action = &cur_poll.actions[i];
+            let call = Promise::new(action.receiver_id.clone()).function_call(
+                action.method_name.clone().into_bytes(),
+                action.args.clone().into(),
+                action.deposit.0,
+                action.gas.0,
+            );
+            let call = match promise {
+                Some(promise) => promise.then(call),
+                None => call,
+            };
+            promise = Some(call.then(ext_self::on_action_executed(
+                index,
+                i as u32,
+                &env::current_account_id(),
+                NO_DEPOSIT,
+                GAS_FOR_RESOLVE_ACTION,
+            )));
+            cur_poll.action_results[i] = ActionStatus::Executing;
+        }
+        cur_poll.execution_started_at = Some(now);
+        self.internal_save_poll(index, &cur_poll);
+
+        promise.unwrap()
+    }
+
+    /// Records the result of a proposal action.
+    #[private]
+    pub fn on_action_executed(&mut self, index: u32, action_index: u32) -> bool {
+        assert_eq!(
+            env::promise_results_count(),
+            1,
+            "Contract expected a result on the callback"
+        );
+        let succeeded = match env::promise_result(0) {
+            PromiseResult::Successful(_) => true,
+            _ => false,
+        };
+
+        let mut cur_poll: Poll = self.internal_get_poll(index);
+        cur_poll.action_results[action_index as usize] = if succeeded {
+            ActionStatus::Succeeded
+        } else {
+            ActionStatus::Failed
+        };
+        self.internal_save_poll(index, &cur_poll);
+
+        env::log(
+            format!(
+                "Poll {} action {} {}",
+                index,
+                action_index,
+                if succeeded { "succeeded" } else { "failed" }
+            )
+            .as_bytes(),
+        );
+        succeeded
+    }
+
+    /// Owner's or guardian's method.
+    /// Gives up on a passed poll whose actions failed, or can't run because they call the voting
+    /// token or this contract, and marks it `ExecutionFailed`. Actions whose result is still unknown
+    /// `EXECUTION_TIMEOUT` after they were scheduled count as failed. The reason is logged.
+    pub fn cancel_execution(&mut self, index: u32, reason: String) {
+        let caller = env::predecessor_account_id();
+        assert!(
+            caller == self.owner || Some(&caller) == self.guardian.as_ref(),
+            "ERR_NOT_ALLOWED"
+        );
+        self.assert_index(index);
+        assert_reason(&reason);
+
+        let mut cur_poll: Poll = self.internal_get_poll(index);
+        assert_eq!(cur_poll.status, PollStatus::Passed, "ERR_POLL_NOT_PASSED");
+        assert!(!self.is_in_timelock(&cur_poll), "ERR_EXECUTION_TIMELOCK");
+        assert!(!cur_poll.is_executing(env::block_timestamp()), "ERR_ACTIONS_ALREADY_EXECUTING");
+        for status in cur_poll.action_results.iter_mut() {
+            if *status == ActionStatus::Executing {
+                *status = ActionStatus::Failed;
+            }
+        }
+        let blocked = [self.token_id.clone(), env::current_account_id()];
+        assert!(
+            cur_poll
+                .actions
+                .iter()
+                .zip(cur_poll.action_results.iter())
+                .any(|(action, status)| *status == ActionStatus::Failed
+                    || (*status != ActionStatus::Succeeded && blocked.contains(&action.receiver_id))),
+            "ERR_NO_FAILED_ACTION"
+        );
+        cur_poll.status = PollStatus::ExecutionFailed;
+        self.internal_save_poll(index, &cur_poll);
+
+        log_poll_closed(index, &cur_poll.status, &caller, &reason);
+        if cur_poll.deposit_status == DepositStatus::Locked {
+            self.internal_settle_deposit(index);
+        }
+    }
+
+    /// Returns the actions of the given poll with their execution results.
+    pub fn get_actions(&self, index: u32) -> Vec<HumanReadableAction> {
+        let cur_poll: Poll = self.internal_get_poll(index);
+        cur_poll
+            .actions
+            .into_iter()
+            .zip(cur_poll.action_results.into_iter())
+            .map(|(action, status)| HumanReadableAction { action, status })
+            .collect()
+    }
+}
+
+impl Poll {
+    /// Returns true if some scheduled actions have no result yet and `EXECUTION_TIMEOUT` hasn't
+    /// elapsed since they were scheduled.
+    pub(crate) fn is_executing(&self, now: Timestamp) -> bool {
+        self.action_results.iter().any(|status| *status == ActionStatus::Executing)
+            && self
+                .execution_started_at
+                .map_or(false, |started_at| now < started_at + EXECUTION_TIMEOUT)
+    }
+}
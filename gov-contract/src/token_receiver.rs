@@ -4,55 +4,208 @@ use near_sdk::{serde_json, PromiseOrValue};
 use near_sdk::json_types::ValidAccountId;
 
 use crate::*;
-use crate::action::UserAction;
+use crate::proposal::ProposalAction;
+use crate::utils::{
+    MAX_ACTIONS, MAX_ACTION_ARGS_LENGTH, MAX_DESCRIPTION_LENGTH, MAX_METHOD_NAME_LENGTH, MAX_OPTION_LENGTH, MAX_TITLE_LENGTH,
+};
 
-pub const VIRTUAL_ACC: &str = "@";
+/// Message attached to a transfer of `token_id`, e.g. `{"V1": {"Vote": {"index": 0, "vote": true}}}`.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub enum TokenReceiverMessage {
+    V1(UserAction),
+}
 
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
-#[serde(untagged)]
 pub enum UserAction {
-    CreatePoll{title: String, description: String},
-    Vote{index: u32, vote: bool},
+    CreatePoll{
+        title: String,
+        description: String,
+        #[serde(default)]
+        actions: Vec<ProposalAction>,
+        #[serde(default)]
+        kind: PollKind,
+        #[serde(default)]
+        voting_mode: VotingMode,
+    },
+    CastBallot{
+        index: u32,
+        ballot: Ballot,
+        /// Only used by polls in conviction voting mode.
+        conviction: Option<u32>,
+    },
+    /// Shorthand for voting on a yes/no poll.
+    Vote{index: u32, vote: bool, conviction: Option<u32>},
+}
+
+/// Why a message was rejected. The transferred tokens are refunded in full.
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub enum RejectedMessage {
+    ContractPaused,
+    IllegalToken { token_id: AccountId },
+    NotValidMessage { error: String },
+    EmptyTitle,
+    TitleTooLong { max_length: usize },
+    DescriptionTooLong { max_length: usize },
+    NotEnoughDeposit { required: U128, attached: U128 },
+    NotValidPollKind { error: &'static str },
+    OptionTooLong { option_index: u32, max_length: usize },
+    TooManyActions { max_actions: usize },
+    MethodNameTooLong { action_index: u32, max_length: usize },
+    ActionArgsTooLong { action_index: u32, max_length: usize },
+    ActionTargetsVotingToken { action_index: u32 },
+    ActionTargetsVotingContract { action_index: u32 },
+    NotValidPollIndex { index: u32 },
+    PollNotInProgress { index: u32, status: PollStatus },
+    VotingClosed { index: u32 },
+    NotYesNoPoll { index: u32 },
+    NotValidBallot { error: &'static str },
+    NotValidConviction { error: &'static str },
 }
 
 #[near_bindgen]
 impl FungibleTokenReceiver for VotingContract {
     /// Callback on receiving tokens by this contract.
-    /// `msg` format is either "" for deposit or `TokenReceiverMessage`.
-    #[allow(unreachable_code)]
+    /// `msg` format is a `TokenReceiverMessage`. A rejected message is logged as a `RejectedMessage`
+    /// and the whole amount is refunded.
     fn ft_on_transfer(
         &mut self,
         sender_id: ValidAccountId,
         amount: U128,
         msg: String,
     ) -> PromiseOrValue<U128> {
-        self.assert_contract_running();
+        match self.internal_handle_message(sender_id.into(), amount.into(), &msg) {
+            Ok(refund) => PromiseOrValue::Value(U128(refund)),
+            Err(rejected) => {
+                env::log(
+                    format!("Rejected message: {}", serde_json::to_string(&rejected).unwrap()).as_bytes(),
+                );
+                PromiseOrValue::Value(amount)
+            }
+        }
+    }
+}
+
+impl VotingContract {
+    /// Handles a message and returns the amount to refund.
+    fn internal_handle_message(&mut self, sender_id: AccountId, amount: Balance, msg: &str) -> Result<Balance, RejectedMessage> {
+        if self.paused {
+            return Err(RejectedMessage::ContractPaused);
+        }
         let token_in = env::predecessor_account_id();
-        if msg.is_empty() {
-            env::panic(b"ERR_JSON_IS_EMPTY");
-        } else {
-            let message =
-                serde_json::from_str::<UserAction>(&msg).expect("ERR_JSON_IS_EMPTY");
-            match message {
-                UserAction::CreatePoll {
-                    title,
-                    description,
-                } => {
-                    self.internal_create_poll(title, description, amount.into(), sender_id.to_string());
-                    PromiseOrValue::Value(U128(0))
-                },
-                UserAction::Vote {
-                    index,
-                    vote
-                } => {
-                    self.assert_index(index);
-                    self.assert_status(index);
-
-                    self.internal_vote(index, AccountInfo(vote, amount.into()), sender_id.to_string());
-                    PromiseOrValue::Value(U128(0))
+        if token_in != self.token_id {
+            return Err(RejectedMessage::IllegalToken { token_id: token_in });
+        }
+        let TokenReceiverMessage::V1(action) = serde_json::from_str::<TokenReceiverMessage>(msg)
+            .map_err(|error| RejectedMessage::NotValidMessage { error: error.to_string() })?;
+
+        match action {
+            UserAction::CreatePoll {
+                title,
+                description,
+                actions,
+                kind,
+                voting_mode,
+            } => {
+                if title.trim().is_empty() {
+                    return Err(RejectedMessage::EmptyTitle);
+                }
+                if title.len() > MAX_TITLE_LENGTH {
+                    return Err(RejectedMessage::TitleTooLong { max_length: MAX_TITLE_LENGTH });
+                }
+                if description.len() > MAX_DESCRIPTION_LENGTH {
+                    return Err(RejectedMessage::DescriptionTooLong { max_length: MAX_DESCRIPTION_LENGTH });
                 }
+                if amount < self.min_create_poll_amount {
+                    return Err(RejectedMessage::NotEnoughDeposit {
+                        required: self.min_create_poll_amount.into(),
+                        attached: amount.into(),
+                    });
+                }
+                kind.validate().map_err(|error| RejectedMessage::NotValidPollKind { error })?;
+                if let PollKind::MultiChoice { options, .. } = &kind {
+                    if let Some(option_index) = options.iter().position(|option| option.len() > MAX_OPTION_LENGTH) {
+                        return Err(RejectedMessage::OptionTooLong {
+                            option_index: option_index as u32,
+                            max_length: MAX_OPTION_LENGTH,
+                        });
+                    }
+                }
+                if actions.len() > MAX_ACTIONS {
+                    return Err(RejectedMessage::TooManyActions { max_actions: MAX_ACTIONS });
+                }
+                if let Some(action_index) = actions.iter().position(|action| action.method_name.len() > MAX_METHOD_NAME_LENGTH) {
+                    return Err(RejectedMessage::MethodNameTooLong {
+                        action_index: action_index as u32,
+                        max_length: MAX_METHOD_NAME_LENGTH,
+                    });
+                }
+                if let Some(action_index) = actions.iter().position(|action| action.args.0.len() > MAX_ACTION_ARGS_LENGTH) {
+                    return Err(RejectedMessage::ActionArgsTooLong {
+                        action_index: action_index as u32,
+                        max_length: MAX_ACTION_ARGS_LENGTH,
+                    });
+                }
+                if let Some(action_index) = actions.iter().position(|action| action.receiver_id == self.token_id) {
+                    return Err(RejectedMessage::ActionTargetsVotingToken { action_index: action_index as u32 });
+                }
+                if let Some(action_index) = actions.iter().position(|action| action.receiver_id == env::current_account_id()) {
+                    return Err(RejectedMessage::ActionTargetsVotingContract { action_index: action_index as u32 });
+                }
+
+                self.internal_create_poll(title, description, kind, voting_mode, actions, None, self.min_create_poll_amount, sender_id);
+                // Refund the excess over the required deposit.
+                Ok(amount - self.min_create_poll_amount)
             }
+            UserAction::CastBallot {
+                index,
+                ballot,
+                conviction
+            } => {
+                self.internal_check_vote(index, &ballot, conviction, &sender_id)?;
+                self.internal_vote(index, ballot, amount, conviction, sender_id);
+                Ok(0)
+            }
+            UserAction::Vote {
+                index,
+                vote,
+                conviction
+            } => {
+                if let Some(PollKind::MultiChoice { .. }) = self.polls.get(index as u64).map(|poll| poll.kind) {
+                    return Err(RejectedMessage::NotYesNoPoll { index });
+                }
+                let ballot = Ballot::yes_no(vote);
+                self.internal_check_vote(index, &ballot, conviction, &sender_id)?;
+                self.internal_vote(index, ballot, amount, conviction, sender_id);
+                Ok(0)
+            }
+        }
+    }
+
+    /// Checks everything `internal_vote` asserts, so a rejected vote is reported instead of panicking.
+    fn internal_check_vote(
+        &self,
+        index: u32,
+        ballot: &Ballot,
+        conviction: Option<u32>,
+        account_id: &AccountId,
+    ) -> Result<(), RejectedMessage> {
+        let cur_poll = self.polls.get(index as u64).ok_or(RejectedMessage::NotValidPollIndex { index })?;
+        if cur_poll.status != PollStatus::InProgress {
+            return Err(RejectedMessage::PollNotInProgress { index, status: cur_poll.status });
         }
+        let now = env::block_timestamp();
+        if now < cur_poll.start_time || now >= cur_poll.end_time {
+            return Err(RejectedMessage::VotingClosed { index });
+        }
+        ballot
+            .validate(cur_poll.kind.num_options())
+            .map_err(|error| RejectedMessage::NotValidBallot { error })?;
+        let current = cur_poll.votes.get(account_id).map_or(0, |info| info.conviction);
+        cur_poll
+            .validate_conviction(conviction.unwrap_or(current), current)
+            .map_err(|error| RejectedMessage::NotValidConviction { error })
     }
-}
\ No newline at end of file
+}
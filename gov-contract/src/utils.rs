@@ -1,4 +1,4 @@
-use near_sdk::Gas;
+use near_sdk::{Gas, PromiseOrValue, PromiseResult};
 use uint::construct_uint;
 use crate::*;
 
@@ -10,6 +10,32 @@ pub const GAS_FOR_FT_TRANSFER: Gas = 10_000_000_000_000;
 pub const GAS_FOR_RESOLVE_TRANSFER: Gas = 10_000_000_000_000;
 /// Amount of gas for seed token transfers resolve.
 pub const GAS_FOR_RESOLVE_WITHDRAW_SEED: Gas = 80_000_000_000_000;
+/// Amount of gas for querying the supply a quorum is measured against.
+pub const GAS_FOR_GET_SUPPLY: Gas = 10_000_000_000_000;
+/// Amount of gas for finalizing a poll once the supply is known.
+pub const GAS_FOR_RESOLVE_FINALIZE: Gas = 40_000_000_000_000;
+/// Amount of gas for locking or valuing the staked balance of an account in a stake source.
+pub const GAS_FOR_GET_VOTING_POWER: Gas = 10_000_000_000_000;
+/// Amount of gas for locking the staked balance and the receipts of an account in a pair stake pool.
+pub const GAS_FOR_LOCK_POOL_STAKE: Gas = 40_000_000_000_000;
+/// Amount of gas for valuing the shares locked in the Synchro staking contract.
+pub const GAS_FOR_RESOLVE_STAKING_LOCK: Gas = 20_000_000_000_000;
+/// Amount of gas for counting the queried staked voting power.
+pub const GAS_FOR_RESOLVE_VOTING_POWER: Gas = 20_000_000_000_000;
+/// Amount of gas for asking a pair stake pool which asset it stakes.
+pub const GAS_FOR_GET_POOL_ASSET: Gas = 10_000_000_000_000;
+/// Amount of gas for setting the stake sources once the pools answered.
+pub const GAS_FOR_RESOLVE_STAKE_SOURCES: Gas = 10_000_000_000_000;
+/// How long the results of scheduled proposal actions are awaited before they can be retried, in nanoseconds.
+pub const EXECUTION_TIMEOUT: u64 = 3_600_000_000_000;
+/// Amount of gas for recording the result of a proposal action.
+pub const GAS_FOR_RESOLVE_ACTION: Gas = 10_000_000_000_000;
+/// Amount of gas the market gets to apply the outcome of a market parameter poll.
+pub const GAS_FOR_MARKET_CALLBACK: Gas = 20_000_000_000_000;
+/// Amount of gas the market gets to record how a market parameter poll ended.
+pub const GAS_FOR_MARKET_OUTCOME: Gas = 10_000_000_000_000;
+/// There is no deposit balance attached.
+pub const NO_DEPOSIT: Balance = 0;
 
 pub const INITIAL_MIN_CREATE_POLL: Balance = 100_000_000_000_000_000_000_000_000;
 
@@ -17,10 +43,259 @@ construct_uint! {
     pub struct U256(4);
 }
 
+/// Maximum length of a poll title, in bytes.
+pub const MAX_TITLE_LENGTH: usize = 128;
+/// Maximum length of a poll description, in bytes.
+pub const MAX_DESCRIPTION_LENGTH: usize = 4096;
+/// Maximum number of options of a multi-choice poll.
+pub const MAX_POLL_OPTIONS: usize = 16;
+/// Maximum length of an option of a multi-choice poll, in bytes.
+pub const MAX_OPTION_LENGTH: usize = 128;
+/// Maximum number of actions of a poll.
+pub const MAX_ACTIONS: usize = 8;
+/// Maximum length of the method name of a proposal action, in bytes.
+pub const MAX_METHOD_NAME_LENGTH: usize = 256;
+/// Maximum length of the arguments of a proposal action, in bytes.
+pub const MAX_ACTION_ARGS_LENGTH: usize = 4096;
+
+/// Maximum length of the reason given for stopping, vetoing or cancelling a poll.
+pub const MAX_REASON_LENGTH: usize = 280;
+
+/// Storage prefix of the votes of the poll with the given index.
+pub(crate) fn poll_votes_prefix(index: u32) -> Vec<u8> {
+    let mut prefix = b"v".to_vec();
+    prefix.extend_from_slice(&index.to_le_bytes());
+    prefix
+}
+
+/// Returns the square root of `value` rounded down, using Newton's method.
+pub(crate) fn integer_sqrt(value: u128) -> u128 {
+    if value < 2 {
+        return value;
+    }
+    // Start from a power of two above the root, the iterations then decrease to it.
+    let bits = 128 - value.leading_zeros();
+    let mut root = 1u128 << ((bits + 1) / 2);
+    loop {
+        let next = (root + value / root) / 2;
+        if next >= root {
+            return root;
+        }
+        root = next;
+    }
+}
+
+pub(crate) fn assert_reason(reason: &str) {
+    assert!(!reason.trim().is_empty(), "ERR_REASON_REQUIRED");
+    assert!(reason.len() <= MAX_REASON_LENGTH, "ERR_REASON_TOO_LONG");
+}
+
+/// Logged as JSON when the owner, the guardian or the creator closes a poll, with the reason given.
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PollClosedEvent<'a> {
+    pub index: u32,
+    pub status: &'a PollStatus,
+    pub account_id: &'a AccountId,
+    pub reason: &'a str,
+}
+
+pub(crate) fn log_poll_closed(index: u32, status: &PollStatus, account_id: &AccountId, reason: &str) {
+    let event = PollClosedEvent { index, status, account_id, reason };
+    env::log(format!("Poll closed: {}", near_sdk::serde_json::to_string(&event).unwrap()).as_bytes());
+}
+
 /// TODO: this should be in the near_standard_contracts
 #[ext_contract(ext_fungible_token)]
 pub trait FungibleToken {
     fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>);
+    fn ft_total_supply(&self) -> U128;
+}
+
+/// Interface for the Synchro staking contract.
+#[ext_contract(ext_staking)]
+pub trait Staking {
+    fn get_locked_token_amount(&self) -> U128;
+    fn get_staked_value(&self, account_id: AccountId) -> U128;
+    /// Locks the shares of the account until `unlock_time` and returns their number.
+    fn lock_for_vote(&mut self, account_id: AccountId, unlock_time: U64) -> U128;
+}
+
+/// Interface for the pair stake pool.
+#[ext_contract(ext_stake_pool)]
+pub trait StakePool {
+    /// Locks the staked balance and the receipts of the account until `unlock_time` and resolves
+    /// to the staked balance they are worth.
+    fn lock_for_vote(&mut self, account_id: AccountId, unlock_time: U64) -> U128;
+    fn get_asset(&self) -> PoolAsset;
+}
+
+/// Interface for the market.
+#[ext_contract(ext_market)]
+pub trait Market {
+    /// Tells the market how a market parameter poll ended once it is finalized, passed or not.
+    fn on_market_poll_finalized(&mut self, index: u32, parameter: MarketParameter, status: PollStatus);
+}
+
+/// Interface for the contract itself.
+#[ext_contract(ext_self)]
+pub trait SelfContract {
+    /// A callback to close the poll with the supply the quorum is measured against.
+    fn on_finalize_poll(&mut self, index: u32, #[callback] supply: U128) -> PollStatus;
+
+    /// A callback to restore the vote if returning the voted tokens failed.
+    fn on_vote_withdrawn(&mut self, index: u32, account_id: AccountId, ballot: Ballot, amount: U128);
+
+    /// A callback to value the shares locked in the Synchro staking contract.
+    fn on_staking_shares_locked(&mut self, account_id: AccountId, contract_id: AccountId) -> PromiseOrValue<U128>;
+
+    /// A callback to vote with the staked balances queried from the stake sources.
+    fn on_voting_power(&mut self, index: u32, account_id: AccountId, ballot: Ballot);
+
+    /// A callback to lock the voting tokens again if returning them failed.
+    fn on_vote_deposit_withdrawn(&mut self, index: u32, account_id: AccountId);
+
+    /// A callback to settle the proposal deposit, or lock it again if the transfer failed.
+    fn on_deposit_transferred(&mut self, index: u32, settled_status: DepositStatus);
+
+    /// A callback to record the result of a proposal action.
+    fn on_action_executed(&mut self, index: u32, action_index: u32) -> bool;
+
+    /// A callback to set the stake sources once the pair stake pools among them answered which
+    /// asset they stake.
+    fn on_stake_sources_checked(&mut self, stake_sources: Vec<StakeSource>);
+}
+
+#[near_bindgen]
+impl VotingContract {
+    /*************/
+    /* Callbacks */
+    /*************/
+    #[private]
+    pub fn on_finalize_poll(&mut self, index: u32, #[callback] supply: U128) -> PollStatus {
+        self.internal_finalize_poll(index, supply.into())
+    }
+
+    #[private]
+    pub fn on_vote_withdrawn(&mut self, index: u32, account_id: AccountId, ballot: Ballot, amount: U128) {
+        assert_eq!(
+            env::promise_results_count(),
+            1,
+            "Contract expected a result on the callback"
+        );
+        if let PromiseResult::Failed = env::promise_result(0) {
+            // This reverts the withdrawal, merging into a vote cast in the meantime.
+            let mut cur_poll: Poll = self.internal_get_poll(index);
+            let mut info = cur_poll.votes.get(&account_id).unwrap_or(AccountInfo {
+                ballot,
+                amount: 0,
+                staked_power: 0,
+                weight: 0,
+                conviction: 0,
+            });
+            cur_poll.remove_from_tally(&info);
+            info.amount += amount.0;
+            info.weight = cur_poll.voting_weight(&info);
+            cur_poll.add_to_tally(&info);
+            cur_poll.votes.insert(&account_id, &info);
+            self.internal_save_poll(index, &cur_poll);
+            self.internal_add_locked_poll(&account_id, index);
+
+            env::log(
+                format!("Returning {} to @{} failed, vote restored", amount.0, account_id).as_bytes(),
+            );
+        }
+    }
+
+    #[private]
+    pub fn on_staking_shares_locked(&mut self, account_id: AccountId, contract_id: AccountId) -> PromiseOrValue<U128> {
+        assert_eq!(
+            env::promise_results_count(),
+            1,
+            "Contract expected a result on the callback"
+        );
+        let shares: U128 = match env::promise_result(0) {
+            PromiseResult::Successful(value) => {
+                near_sdk::serde_json::from_slice(&value).expect("ERR_NOT_VALID_BALANCE")
+            }
+            _ => env::panic(b"ERR_VOTING_POWER_QUERY_FAILED"),
+        };
+        if shares.0 == 0 {
+            return PromiseOrValue::Value(shares);
+        }
+        ext_staking::get_staked_value(account_id, &contract_id, NO_DEPOSIT, GAS_FOR_GET_VOTING_POWER).into()
+    }
+
+    #[private]
+    pub fn on_voting_power(&mut self, index: u32, account_id: AccountId, ballot: Ballot) {
+        let mut staked_power: Balance = 0;
+        for i in 0..env::promise_results_count() {
+            match env::promise_result(i) {
+                PromiseResult::Successful(value) => {
+                    let balance: U128 = near_sdk::serde_json::from_slice(&value).expect("ERR_NOT_VALID_BALANCE");
+                    staked_power += balance.0;
+                }
+                _ => env::panic(b"ERR_VOTING_POWER_QUERY_FAILED"),
+            }
+        }
+        assert!(staked_power > 0, "ERR_NO_STAKE");
+        // The poll may have closed while the stake was queried.
+        self.assert_status(index);
+        self.assert_voting_period(index);
+
+        self.internal_vote_with_stake(index, ballot, staked_power, account_id);
+    }
+
+    #[private]
+    pub fn on_deposit_transferred(&mut self, index: u32, settled_status: DepositStatus) {
+        assert_eq!(
+            env::promise_results_count(),
+            1,
+            "Contract expected a result on the callback"
+        );
+        let mut cur_poll: Poll = self.internal_get_poll(index);
+        cur_poll.deposit_status = match env::promise_result(0) {
+            PromiseResult::Successful(_) => settled_status,
+            _ => {
+                env::log(format!("Transferring deposit of poll {} failed, deposit locked again", index).as_bytes());
+                DepositStatus::Locked
+            }
+        };
+        self.internal_save_poll(index, &cur_poll);
+    }
+
+    #[private]
+    pub fn on_stake_sources_checked(&mut self, stake_sources: Vec<StakeSource>) {
+        for i in 0..env::promise_results_count() {
+            let asset: PoolAsset = match env::promise_result(i) {
+                PromiseResult::Successful(value) => {
+                    near_sdk::serde_json::from_slice(&value).expect("ERR_NOT_VALID_ASSET")
+                }
+                _ => env::panic(b"ERR_ASSET_QUERY_FAILED"),
+            };
+            match asset {
+                PoolAsset::Token { token_id } if token_id == self.token_id => {}
+                _ => env::panic(b"ERR_POOL_ASSET_NOT_VOTING_TOKEN"),
+            }
+        }
+        self.stake_sources = stake_sources;
+    }
+
+    #[private]
+    pub fn on_vote_deposit_withdrawn(&mut self, index: u32, account_id: AccountId) {
+        assert_eq!(
+            env::promise_results_count(),
+            1,
+            "Contract expected a result on the callback"
+        );
+        if let PromiseResult::Failed = env::promise_result(0) {
+            self.internal_add_locked_poll(&account_id, index);
+            env::log(
+                format!("Returning deposit of poll {} to @{} failed, deposit locked again", index, account_id)
+                    .as_bytes(),
+            );
+        }
+    }
 }
 
 impl VotingContract {
@@ -29,20 +304,43 @@ impl VotingContract {
     }
 
     pub(crate) fn assert_contract_running(&self) {
-        assert!(!self.pause, "ERR_PAUSED_CONTRACT");
+        assert!(!self.paused, "ERR_PAUSED_CONTRACT");
     }
 
     pub(crate) fn assert_index(&self, index: u32) {
-        assert!(index >= 0 && index < self.poll_count, "ERR_NOT_VALID_POLL_INDEX");
+        assert!((index as u64) < self.polls.len(), "ERR_NOT_VALID_POLL_INDEX");
     }
 
     pub(crate) fn assert_status(&self, index: u32) {
-        let cur_poll: Poll = self.polls[index];
+        let cur_poll: Poll = self.internal_get_poll(index);
         match cur_poll.status {
             PollStatus::Passed => env::panic(b"Voting has already passed"),
             PollStatus::Rejected => env::panic(b"Voting has already rejected"),
             PollStatus::Expired => env::panic(b"Voting has already expired"),
+            PollStatus::Vetoed => env::panic(b"Voting has already been vetoed"),
+            PollStatus::Cancelled => env::panic(b"Voting has already been cancelled"),
+            PollStatus::ExecutionFailed => env::panic(b"Voting has already passed and failed to execute"),
             _ => {}
         }
     }
+
+    /// Returns true if the poll is finalized and its execution timelock hasn't ended yet.
+    pub(crate) fn is_in_timelock(&self, cur_poll: &Poll) -> bool {
+        cur_poll
+            .timelock_ends_at()
+            .map_or(false, |ends_at| env::block_timestamp() < ends_at)
+    }
+
+    pub(crate) fn assert_unlocked(&self, cur_poll: &Poll, info: &AccountInfo) {
+        if let Some(locked_until) = cur_poll.locked_until(info) {
+            assert!(env::block_timestamp() >= locked_until, "ERR_VOTE_LOCKED");
+        }
+    }
+
+    pub(crate) fn assert_voting_period(&self, index: u32) {
+        let cur_poll: Poll = self.internal_get_poll(index);
+        let now = env::block_timestamp();
+        assert!(now >= cur_poll.start_time, "ERR_VOTING_NOT_STARTED");
+        assert!(now < cur_poll.end_time, "ERR_VOTING_PERIOD_ENDED");
+    }
 }
\ No newline at end of file
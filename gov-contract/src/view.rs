@@ -1,26 +1,294 @@
 use crate::*;
 
+/// Represents a vote readable by humans.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct HumanReadableVote {
+    pub account_id: AccountId,
+    pub ballot: Ballot,
+    pub amount: U128,
+    /// Voting power queried from the stake sources.
+    pub staked_power: U128,
+    /// Voting power of `amount` and `staked_power` according to the voting mode of the poll.
+    pub weight: U128,
+    pub conviction: u32,
+}
+
+impl HumanReadableVote {
+    fn new(account_id: AccountId, info: AccountInfo) -> Self {
+        HumanReadableVote {
+            account_id,
+            ballot: info.ballot,
+            amount: info.amount.into(),
+            staked_power: info.staked_power.into(),
+            weight: info.weight.into(),
+            conviction: info.conviction,
+        }
+    }
+}
+
+/// Represents a poll readable by humans.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct HumanReadablePoll {
+    pub index: u32,
+    pub creator_id: AccountId,
+    pub status: PollStatus,
+    pub title: String,
+    pub description: String,
+    pub kind: PollKind,
+    pub voting_mode: VotingMode,
+    pub create_date: Option<WrappedTimestamp>,
+    pub start_time: WrappedTimestamp,
+    pub end_time: WrappedTimestamp,
+    pub finalized_at: Option<WrappedTimestamp>,
+    pub deposit_amount: U128,
+    pub deposit_status: DepositStatus,
+    /// Voting power on each option of `kind`.
+    pub option_amounts: Vec<U128>,
+    pub abstain_amount: U128,
+    pub stake_amount: U128,
+    pub total_balance_at_end_poll: U128,
+    pub winner: Option<u32>,
+    pub number_of_votes: u64,
+    pub number_of_actions: u32,
+    /// Market change voted on, for polls opened by the market.
+    pub market_parameter: Option<MarketParameter>,
+}
+
+impl HumanReadablePoll {
+    fn new(index: u32, poll: Poll) -> Self {
+        HumanReadablePoll {
+            index,
+            number_of_votes: poll.votes.len(),
+            number_of_actions: poll.actions.len() as u32,
+            creator_id: poll.creator_id,
+            status: poll.status,
+            title: poll.title,
+            description: poll.description,
+            kind: poll.kind,
+            voting_mode: poll.voting_mode,
+            create_date: poll.create_date,
+            start_time: poll.start_time.into(),
+            end_time: poll.end_time.into(),
+            finalized_at: poll.finalized_at.map(|finalized_at| finalized_at.into()),
+            deposit_amount: poll.deposit_amount.into(),
+            deposit_status: poll.deposit_status,
+            option_amounts: poll.option_amounts.into_iter().map(|amount| amount.into()).collect(),
+            abstain_amount: poll.abstain_amount.into(),
+            stake_amount: poll.stake_amount.into(),
+            total_balance_at_end_poll: poll.total_balance_at_end_poll.into(),
+            winner: poll.winner,
+            market_parameter: poll.market_parameter,
+        }
+    }
+}
+
+/// A vote of an account together with the poll it was cast on.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AccountVote {
+    pub index: u32,
+    pub status: PollStatus,
+    #[serde(flatten)]
+    pub vote: HumanReadableVote,
+}
+
+/// Voting power on one option of a poll.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OptionResult {
+    pub option: String,
+    pub amount: U128,
+}
+
+/// Per-option results of a poll.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PollResults {
+    pub kind: PollKind,
+    pub voting_mode: VotingMode,
+    pub options: Vec<OptionResult>,
+    pub abstain_amount: U128,
+    pub status: PollStatus,
+    /// Winning option, set when the poll passes.
+    pub winner: Option<u32>,
+}
+
+/// Voting tokens an account has locked in a poll.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LockedBalance {
+    pub index: u32,
+    pub amount: U128,
+    /// Whether the poll has ended and the conviction lock has elapsed, so the tokens can be withdrawn.
+    pub can_withdraw: bool,
+}
+
 #[near_bindgen]
 impl VotingContract {
     /// Get the timestamp of when the voting finishes. `None` means the voting hasn't ended yet.
     pub fn get_result(&self, index: u32) -> PollStatus {
-        let mut cur_poll: Poll = self.polls[index];
-        cur_poll.status.clone()
+        self.internal_get_poll(index).status
     }
 
-    /// Returns current a pair of `total_voted_stake` and the total stake.
-    /// Note: as a view method, it doesn't recompute the active stake. May need to call `ping` to
-    /// update the active stake.
+    /// Returns the total stake voted on the given poll.
     pub fn get_total_voted_stake(&self, index: u32) -> U128 {
-        let mut cur_poll: Poll = self.polls[index];
-        cur_poll.stake_amount;
+        self.internal_get_poll(index).stake_amount.into()
+    }
+
+    /// Returns the `(yes, no)` tally of the given yes/no poll.
+    pub fn get_tally(&self, index: u32) -> (U128, U128) {
+        let cur_poll: Poll = self.internal_get_poll(index);
+        match cur_poll.kind {
+            PollKind::YesNo => {}
+            PollKind::MultiChoice { .. } => env::panic(b"ERR_NOT_YES_NO_POLL"),
+        }
+        (cur_poll.option_amounts[0].into(), cur_poll.option_amounts[1].into())
+    }
+
+    /// Returns the voting power on each option of the given poll and its winner, if any.
+    pub fn get_results(&self, index: u32) -> PollResults {
+        let cur_poll: Poll = self.internal_get_poll(index);
+        PollResults {
+            options: cur_poll
+                .option_amounts
+                .iter()
+                .enumerate()
+                .map(|(option, amount)| OptionResult {
+                    option: cur_poll.kind.option_name(option),
+                    amount: (*amount).into(),
+                })
+                .collect(),
+            abstain_amount: cur_poll.abstain_amount.into(),
+            status: cur_poll.status,
+            winner: cur_poll.winner,
+            kind: cur_poll.kind,
+            voting_mode: cur_poll.voting_mode,
+        }
+    }
+
+    /// Returns the vote of the given account on the given poll, if any.
+    pub fn get_vote(&self, index: u32, account_id: AccountId) -> Option<HumanReadableVote> {
+        self.internal_get_poll(index)
+            .votes
+            .get(&account_id)
+            .map(|info| HumanReadableVote::new(account_id, info))
+    }
+
+    /// Returns the given poll.
+    pub fn get_poll(&self, index: u32) -> HumanReadablePoll {
+        HumanReadablePoll::new(index, self.internal_get_poll(index))
+    }
+
+    /// Returns up to `limit` polls with indices from `from_index` on, keeping only the ones with
+    /// the given status if `status_filter` is set.
+    pub fn get_polls(&self, from_index: u32, limit: u32, status_filter: Option<PollStatus>) -> Vec<HumanReadablePoll> {
+        (from_index..self.polls.len() as u32)
+            .map(|index| (index, self.internal_get_poll(index)))
+            .filter(|(_, poll)| status_filter.as_ref().map_or(true, |status| poll.status == *status))
+            .take(limit as usize)
+            .map(|(index, poll)| HumanReadablePoll::new(index, poll))
+            .collect()
+    }
+
+    /// Returns the current votes of the given account across polls.
+    pub fn get_account_votes(&self, account_id: AccountId) -> Vec<AccountVote> {
+        self.account_voted_polls
+            .get(&account_id)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|index| {
+                let cur_poll: Poll = self.internal_get_poll(index);
+                cur_poll.votes.get(&account_id).map(|info| AccountVote {
+                    index,
+                    status: cur_poll.status,
+                    vote: HumanReadableVote::new(account_id.clone(), info),
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the account that can veto passed polls during their execution timelock.
+    pub fn get_guardian(&self) -> Option<AccountId> {
+        self.guardian.clone()
+    }
+
+    /// Returns the contracts whose staked positions count as voting power.
+    pub fn get_stake_sources(&self) -> Vec<StakeSource> {
+        self.stake_sources.clone()
+    }
+
+    /// Returns the voting rules applied to newly created polls.
+    pub fn get_poll_config(&self) -> PollConfig {
+        self.poll_config.clone()
+    }
+
+    /// Returns the voting period of the given poll as `(start_time, end_time)` in nanoseconds.
+    pub fn get_voting_period(&self, index: u32) -> (WrappedTimestamp, WrappedTimestamp) {
+        let cur_poll: Poll = self.internal_get_poll(index);
+        (cur_poll.start_time.into(), cur_poll.end_time.into())
+    }
+
+    /// Returns the number of polls.
+    pub fn get_number_of_polls(&self) -> u64 {
+        self.polls.len()
+    }
+
+    /// Returns the number of accounts that voted on the given poll.
+    pub fn get_number_of_votes(&self, index: u32) -> u64 {
+        self.internal_get_poll(index).votes.len()
+    }
+
+    /// Returns the list of votes of the given poll.
+    pub fn get_votes(&self, index: u32, from_index: u64, limit: u64) -> Vec<HumanReadableVote> {
+        let cur_poll: Poll = self.internal_get_poll(index);
+        let keys = cur_poll.votes.keys_as_vector();
+        let values = cur_poll.votes.values_as_vector();
+
+        (from_index..std::cmp::min(from_index.saturating_add(limit), keys.len()))
+            .map(|index| HumanReadableVote::new(keys.get(index).unwrap(), values.get(index).unwrap()))
+            .collect()
+    }
+
+    /// Returns the voting tokens the given account has locked in each poll.
+    pub fn get_locked_balances(&self, account_id: AccountId) -> Vec<LockedBalance> {
+        self.internal_get_locked_polls(&account_id)
+            .into_iter()
+            .map(|index| {
+                let cur_poll: Poll = self.internal_get_poll(index);
+                let info = cur_poll.votes.get(&account_id);
+                let unlocked = info
+                    .as_ref()
+                    .and_then(|info| cur_poll.locked_until(info))
+                    .map_or(true, |locked_until| env::block_timestamp() >= locked_until);
+                LockedBalance {
+                    index,
+                    amount: info.map(|info| info.amount).unwrap_or(0).into(),
+                    can_withdraw: cur_poll.status != PollStatus::InProgress && unlocked,
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the total voting tokens the given account has locked across polls.
+    pub fn get_total_locked_balance(&self, account_id: AccountId) -> U128 {
+        self.get_locked_balances(account_id).iter().map(|locked| locked.amount.0).sum::<Balance>().into()
+    }
+
+    /// Returns the proposal deposit of the given poll and where it is in its lifecycle.
+    pub fn get_poll_deposit(&self, index: u32) -> (U128, DepositStatus) {
+        let cur_poll: Poll = self.internal_get_poll(index);
+        (cur_poll.deposit_amount.into(), cur_poll.deposit_status)
+    }
+
+    /// Returns the total amount of burned proposal deposits.
+    pub fn get_burned_amount(&self) -> U128 {
+        self.burned_amount.into()
     }
 
-    /// Returns all active votes.
-    /// Note: as a view method, it doesn't recompute the active stake. May need to call `ping` to
-    /// update the active stake.
-    pub fn get_votes(&self, index: u32) -> HashMap<AccountId, U128> {
-        let mut cur_poll: Poll = self.polls[index];
-        cur_poll.votes.iter().map(|(account_id, info)| (account_id.clone(), info.vote, (*info.amount).into())).collect()
+    /// Returns the total amount of proposal deposits taken from spam polls.
+    pub fn get_slashed_amount(&self) -> U128 {
+        self.slashed_amount.into()
     }
-}
\ No newline at end of file
+}
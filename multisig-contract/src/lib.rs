@@ -0,0 +1,226 @@
+/*!
+Council contract holding the privileged roles of the other contracts.
+Members propose function calls, and a request runs once enough members confirmed it.
+*/
+mod utils;
+mod view;
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::Vector;
+use near_sdk::json_types::{Base64VecU8, ValidAccountId, WrappedDuration, U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, ext_contract, near_bindgen, AccountId, Balance, Gas, Promise, Timestamp};
+
+use crate::utils::{ext_self, GAS_FOR_RESOLVE_REQUEST, NO_DEPOSIT};
+
+near_sdk::setup_alloc!();
+
+/// A function call made by this contract once its request is confirmed.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FunctionCallAction {
+    pub method_name: String,
+    pub args: Base64VecU8,
+    pub deposit: U128,
+    pub gas: U64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum RequestStatus {
+    /// Collecting confirmations.
+    Pending,
+    /// The function calls are scheduled and their result is not known yet.
+    Executing,
+    Executed,
+    /// One of the function calls failed. A new request is needed to retry.
+    Failed,
+    /// Every member revoked its confirmation.
+    Revoked,
+}
+
+/// Function calls to a single receiver, executed together in one batch.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct Request {
+    proposer_id: AccountId,
+    receiver_id: AccountId,
+    actions: Vec<FunctionCallAction>,
+    /// Members that confirmed the request, including the proposer.
+    confirmations: Vec<AccountId>,
+    /// The request can't be confirmed or executed after this time.
+    expires_at: Timestamp,
+    status: RequestStatus,
+}
+
+#[near_bindgen]
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct MultisigContract {
+    members: Vec<AccountId>,
+    /// Number of confirmations of current members a request needs to be executed.
+    threshold: u32,
+    /// How long a request stays valid after it was proposed, in nanoseconds.
+    request_lifetime: WrappedDuration,
+    /// Requests indexed by their id.
+    requests: Vector<Request>,
+}
+
+impl Default for MultisigContract {
+    fn default() -> Self {
+        env::panic(b"Multisig contract should be initialized before usage")
+    }
+}
+
+#[near_bindgen]
+impl MultisigContract {
+    #[init]
+    pub fn new(members: Vec<ValidAccountId>, threshold: u32, request_lifetime: WrappedDuration) -> Self {
+        assert!(!env::state_exists(), "The contract is already initialized");
+        assert!(request_lifetime.0 > 0, "ERR_ZERO_REQUEST_LIFETIME");
+        let mut contract = MultisigContract {
+            members: vec![],
+            threshold,
+            request_lifetime,
+            requests: Vector::new(b"r".to_vec()),
+        };
+        for member in members {
+            contract.internal_add_member(member.into());
+        }
+        contract.assert_threshold();
+        contract
+    }
+
+    /// Member's method.
+    /// Proposes function calls to `receiver_id` and confirms them. Returns the request id.
+    pub fn propose(&mut self, receiver_id: ValidAccountId, actions: Vec<FunctionCallAction>) -> u64 {
+        self.assert_member();
+        assert!(!actions.is_empty(), "ERR_NO_ACTIONS");
+
+        let proposer_id = env::predecessor_account_id();
+        let request = Request {
+            proposer_id: proposer_id.clone(),
+            receiver_id: receiver_id.into(),
+            actions,
+            confirmations: vec![proposer_id.clone()],
+            expires_at: env::block_timestamp() + self.request_lifetime.0,
+            status: RequestStatus::Pending,
+        };
+        let request_id = self.requests.len();
+        self.requests.push(&request);
+
+        env::log(
+            format!("@{} proposed request {} to @{}", proposer_id, request_id, request.receiver_id).as_bytes(),
+        );
+        request_id
+    }
+
+    /// Member's method.
+    /// Confirms a pending request.
+    pub fn confirm(&mut self, request_id: u64) {
+        self.assert_member();
+        let mut request = self.internal_get_pending_request(request_id);
+        let member_id = env::predecessor_account_id();
+        assert!(!request.confirmations.contains(&member_id), "ERR_ALREADY_CONFIRMED");
+
+        request.confirmations.push(member_id.clone());
+        self.requests.replace(request_id, &request);
+        env::log(format!("@{} confirmed request {}", member_id, request_id).as_bytes());
+    }
+
+    /// Member's method.
+    /// Withdraws the confirmation of the predecessor from a pending request.
+    /// The request is revoked once no confirmation is left.
+    pub fn revoke(&mut self, request_id: u64) {
+        self.assert_member();
+        let mut request = self.internal_get_pending_request(request_id);
+        let member_id = env::predecessor_account_id();
+        let len = request.confirmations.len();
+        request.confirmations.retain(|confirmed| *confirmed != member_id);
+        assert_ne!(request.confirmations.len(), len, "ERR_NOT_CONFIRMED");
+
+        if request.confirmations.is_empty() {
+            request.status = RequestStatus::Revoked;
+        }
+        self.requests.replace(request_id, &request);
+        env::log(format!("@{} revoked confirmation of request {}", member_id, request_id).as_bytes());
+    }
+
+    /// Member's method.
+    /// Runs the function calls of a request confirmed by at least `threshold` current members.
+    pub fn execute(&mut self, request_id: u64) -> Promise {
+        self.assert_member();
+        let mut request = self.internal_get_pending_request(request_id);
+        assert!(self.internal_num_confirmations(&request) >= self.threshold, "ERR_NOT_ENOUGH_CONFIRMATIONS");
+        let required_gas: Gas = request.actions.iter().map(|action| action.gas.0).sum::<Gas>() + GAS_FOR_RESOLVE_REQUEST;
+        assert!(env::prepaid_gas() > required_gas, "ERR_NOT_ENOUGH_GAS");
+
+        let mut promise = Promise::new(request.receiver_id.clone());
+        for action in request.actions.iter() {
+            promise = promise.function_call(
+                action.method_name.clone().into_bytes(),
+                action.args.clone().into(),
+                action.deposit.0,
+                action.gas.0,
+            );
+        }
+        request.status = RequestStatus::Executing;
+        self.requests.replace(request_id, &request);
+
+        env::log(format!("Executing request {}", request_id).as_bytes());
+        promise.then(ext_self::on_request_executed(
+            request_id,
+            &env::current_account_id(),
+            NO_DEPOSIT,
+            GAS_FOR_RESOLVE_REQUEST,
+        ))
+    }
+
+    /*****************************************/
+    /* Methods called through requests only. */
+    /*****************************************/
+
+    pub fn add_member(&mut self, member_id: ValidAccountId) {
+        self.assert_self();
+        self.internal_add_member(member_id.into());
+    }
+
+    pub fn remove_member(&mut self, member_id: ValidAccountId) {
+        self.assert_self();
+        let member_id: AccountId = member_id.into();
+        let len = self.members.len();
+        self.members.retain(|member| *member != member_id);
+        assert_ne!(self.members.len(), len, "ERR_NOT_MEMBER");
+        self.assert_threshold();
+    }
+
+    pub fn set_threshold(&mut self, threshold: u32) {
+        self.assert_self();
+        self.threshold = threshold;
+        self.assert_threshold();
+    }
+
+    pub fn set_request_lifetime(&mut self, request_lifetime: WrappedDuration) {
+        self.assert_self();
+        assert!(request_lifetime.0 > 0, "ERR_ZERO_REQUEST_LIFETIME");
+        self.request_lifetime = request_lifetime;
+    }
+}
+
+impl MultisigContract {
+    fn internal_add_member(&mut self, member_id: AccountId) {
+        assert!(!self.members.contains(&member_id), "ERR_ALREADY_MEMBER");
+        self.members.push(member_id);
+    }
+
+    /// Returns the request if it can still be confirmed, revoked or executed.
+    fn internal_get_pending_request(&self, request_id: u64) -> Request {
+        let request = self.requests.get(request_id).expect("ERR_NOT_VALID_REQUEST_ID");
+        assert_eq!(request.status, RequestStatus::Pending, "ERR_REQUEST_NOT_PENDING");
+        assert!(env::block_timestamp() < request.expires_at, "ERR_REQUEST_EXPIRED");
+        request
+    }
+
+    /// Confirmations of members removed since they confirmed don't count.
+    pub(crate) fn internal_num_confirmations(&self, request: &Request) -> u32 {
+        request.confirmations.iter().filter(|member| self.members.contains(member)).count() as u32
+    }
+}
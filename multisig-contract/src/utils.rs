@@ -0,0 +1,60 @@
+use near_sdk::PromiseResult;
+use crate::*;
+
+/// Amount of gas for recording the result of a request.
+pub const GAS_FOR_RESOLVE_REQUEST: Gas = 10_000_000_000_000;
+/// There is no deposit balance attached.
+pub const NO_DEPOSIT: Balance = 0;
+
+/// Interface for the contract itself.
+#[ext_contract(ext_self)]
+pub trait SelfContract {
+    /// A callback to record the result of a request.
+    fn on_request_executed(&mut self, request_id: u64) -> bool;
+}
+
+#[near_bindgen]
+impl MultisigContract {
+    /*************/
+    /* Callbacks */
+    /*************/
+    #[private]
+    pub fn on_request_executed(&mut self, request_id: u64) -> bool {
+        assert_eq!(
+            env::promise_results_count(),
+            1,
+            "Contract expected a result on the callback"
+        );
+        let succeeded = match env::promise_result(0) {
+            PromiseResult::Successful(_) => true,
+            _ => false,
+        };
+
+        let mut request = self.requests.get(request_id).expect("ERR_NOT_VALID_REQUEST_ID");
+        request.status = if succeeded { RequestStatus::Executed } else { RequestStatus::Failed };
+        self.requests.replace(request_id, &request);
+
+        env::log(
+            format!("Request {} {}", request_id, if succeeded { "executed" } else { "failed" }).as_bytes(),
+        );
+        succeeded
+    }
+}
+
+impl MultisigContract {
+    pub(crate) fn assert_member(&self) {
+        assert!(self.members.contains(&env::predecessor_account_id()), "ERR_NOT_MEMBER");
+    }
+
+    /// Changes to the council itself go through confirmed requests.
+    pub(crate) fn assert_self(&self) {
+        assert_eq!(env::predecessor_account_id(), env::current_account_id(), "ERR_NOT_ALLOWED");
+    }
+
+    pub(crate) fn assert_threshold(&self) {
+        assert!(
+            self.threshold > 0 && self.threshold as usize <= self.members.len(),
+            "ERR_NOT_VALID_THRESHOLD"
+        );
+    }
+}
@@ -0,0 +1,59 @@
+use near_sdk::json_types::WrappedTimestamp;
+use crate::*;
+
+/// Represents a request readable by humans.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct HumanReadableRequest {
+    pub request_id: u64,
+    pub proposer_id: AccountId,
+    pub receiver_id: AccountId,
+    pub actions: Vec<FunctionCallAction>,
+    pub confirmations: Vec<AccountId>,
+    /// Confirmations of current members, compared against the threshold.
+    pub num_confirmations: u32,
+    pub expires_at: WrappedTimestamp,
+    pub status: RequestStatus,
+    pub is_expired: bool,
+}
+
+#[near_bindgen]
+impl MultisigContract {
+    pub fn get_members(&self) -> Vec<AccountId> {
+        self.members.clone()
+    }
+
+    pub fn get_threshold(&self) -> u32 {
+        self.threshold
+    }
+
+    pub fn get_request_lifetime(&self) -> WrappedDuration {
+        self.request_lifetime
+    }
+
+    pub fn get_number_of_requests(&self) -> u64 {
+        self.requests.len()
+    }
+
+    pub fn get_request(&self, request_id: u64) -> HumanReadableRequest {
+        let request = self.requests.get(request_id).expect("ERR_NOT_VALID_REQUEST_ID");
+        HumanReadableRequest {
+            request_id,
+            num_confirmations: self.internal_num_confirmations(&request),
+            is_expired: env::block_timestamp() >= request.expires_at,
+            proposer_id: request.proposer_id,
+            receiver_id: request.receiver_id,
+            actions: request.actions,
+            confirmations: request.confirmations,
+            expires_at: request.expires_at.into(),
+            status: request.status,
+        }
+    }
+
+    /// Returns the requests with ids from `from_index` to `from_index + limit`.
+    pub fn get_requests(&self, from_index: u64, limit: u64) -> Vec<HumanReadableRequest> {
+        (from_index..std::cmp::min(from_index + limit, self.requests.len()))
+            .map(|request_id| self.get_request(request_id))
+            .collect()
+    }
+}
@@ -5,12 +5,15 @@ impl StakingContract {
     /********************/
 
     pub(crate) fn internal_deposit(&mut self, account_id: &AccountId, amount: Balance) -> u128 {
-        self.internal_ping();
+        let need_to_restake = self.internal_ping();
 
         let mut account = self.internal_get_account(&account_id);
         account.unstaked += amount;
         self.internal_save_account(&account_id, &account);
-        self.last_total_balance += amount;
+        // Deposited tokens aren't part of the balance of the pool, only native deposits are.
+        if self.asset == Asset::Native {
+            self.last_total_balance += amount;
+        }
 
         env::log(
             format!(
@@ -19,20 +22,60 @@ impl StakingContract {
             )
                 .as_bytes(),
         );
+        if need_to_restake {
+            self.internal_restake();
+        }
         amount
     }
 
-    pub(crate) fn internal_deposit_and_stake(&mut self, account_id: &AccountId, amount: Balance) -> u128 {
-        self.internal_ping();
-
+    pub(crate) fn internal_deposit_and_stake(&mut self, account_id: &AccountId, amount: Balance) {
         let deposit_amount = self.internal_deposit(account_id, amount);
-        self.internal_stake(deposit_amount);
+        self.internal_stake(account_id.clone(), deposit_amount);
+    }
+
+    /// Takes a token deposit that passed the whitelist, staking it if asked to.
+    pub(crate) fn internal_token_deposit(&mut self, account_id: &AccountId, amount: Balance, stake: bool) {
+        if stake {
+            self.internal_deposit_and_stake(account_id, amount);
+        } else {
+            self.internal_deposit(account_id, amount);
+        }
+    }
+
+    /// Logs a token deposit the whitelist didn't allow and returns it as the amount to refund.
+    pub(crate) fn internal_reject_deposit(&self, account_id: &AccountId, amount: U128) -> U128 {
+        env::log(
+            format!(
+                "@{} isn't whitelisted to deposit. Refunding {}",
+                account_id, amount.0
+            )
+                .as_bytes(),
+        );
+        amount
     }
 
     /********************/
     /* Internal methods */
     /********************/
 
+    /// Restakes the current `total_staked_balance` again with the staking key.
+    /// Does nothing while the staking is paused, the balance is restaked when it resumes.
+    /// Does nothing for a token pool either, its staked balance is in tokens, not NEAR.
+    pub(crate) fn internal_restake(&mut self) {
+        if self.paused || self.asset != Asset::Native {
+            return;
+        }
+        // Stakes with the staking public key. If the public key is invalid the entire function
+        // call will be rolled back.
+        Promise::new(env::current_account_id())
+            .stake(self.total_staked_balance, self.stake_public_key.clone())
+            .then(ext_self::on_stake_action(
+                &env::current_account_id(),
+                NO_DEPOSIT,
+                ON_STAKE_ACTION_GAS,
+            ));
+    }
+
     pub(crate) fn internal_withdraw(&mut self, amount: Balance) {
         assert!(amount > 0, "Withdrawal amount should be positive");
 
@@ -42,11 +85,12 @@ impl StakingContract {
             account.unstaked >= amount,
             "Not enough unstaked balance to withdraw"
         );
+        let epoch_height = env::epoch_height();
         assert!(
-            account.unstaked_available_epoch_height <= env::epoch_height(),
+            account.available_balance(epoch_height) >= amount,
             "The unstaked balance is not yet available due to unstaking delay"
         );
-        account.unstaked -= amount;
+        account.take_unstaked(amount, epoch_height);
         self.internal_save_account(&account_id, &account);
 
         env::log(
@@ -57,15 +101,35 @@ impl StakingContract {
                 .as_bytes(),
         );
 
-        Promise::new(account_id).transfer(amount); // TODO: 토큰 전송
-        self.last_total_balance -= amount;
+        match &self.asset {
+            Asset::Native => {
+                Promise::new(account_id).transfer(amount);
+                self.last_total_balance -= amount;
+            }
+            Asset::Token { token_id } => {
+                // The unstaked balance is restored if the transfer fails.
+                ext_fungible_token::ft_transfer(
+                    account_id.clone(),
+                    amount.into(),
+                    None,
+                    token_id,
+                    1,
+                    FT_TRANSFER_GAS,
+                ).then(ext_self::on_withdraw(
+                    account_id,
+                    amount.into(),
+                    &env::current_account_id(),
+                    NO_DEPOSIT,
+                    ON_WITHDRAW_GAS,
+                ));
+            }
+        }
     }
 
-    pub(crate) fn internal_stake(&mut self, amount: Balance) {
+    pub(crate) fn internal_stake(&mut self, account_id: AccountId, amount: Balance) {
         assert!(amount > 0, "Staking amount should be positive");
 
-        let account_id = env::predecessor_account_id();
-        let account = self.internal_get_account(&account_id);
+        let mut account = self.internal_get_account(&account_id);
 
         // Calculate the number of "stake" shares that the account will receive for staking the
         // given amount.
@@ -88,46 +152,118 @@ impl StakingContract {
             "Not enough unstaked balance to stake"
         );
 
-        // Mint bNear to caller.
-        // Stake occurs only when minting action is successful.
-        ext_fungible_token::mint(
+        // The stake is taken right away, so the same balance can't be staked again before the
+        // receipts are minted. `on_mint_action` gives it back if the mint fails.
+        let epoch_height = env::epoch_height();
+        let ticket_amount = charge_amount.saturating_sub(account.available_balance(epoch_height));
+        account.take_unstaked(charge_amount, epoch_height);
+        self.internal_save_account(&account_id, &account);
+        // The new shares are held by the receipts minted for them.
+        self.receipt_shares += num_shares;
+
+        // The staked amount that will be added to the total to guarantee the "stake" share price
+        // never decreases. The difference between `stake_amount` and `charge_amount` is paid
+        // from the allocated STAKE_SHARE_PRICE_GUARANTEE_FUND.
+        let stake_amount = self.staked_amount_from_num_shares_rounded_up(num_shares);
+
+        self.total_staked_balance += stake_amount;
+        self.total_stake_shares += num_shares;
+
+        env::log(
+            format!(
+                "@{} staking {}. Minting {} new staking shares as receipts. Total {} unstaked balance",
+                account_id, charge_amount, num_shares, account.unstaked,
+            )
+                .as_bytes(),
+        );
+        env::log(
+            format!(
+                "Contract total staked balance is {}. Total number of shares {}",
+                self.total_staked_balance, self.total_stake_shares
+            )
+                .as_bytes(),
+        );
+
+        // Mint one bNEAR receipt per "stake" share to the caller.
+        // The balance is restaked only when minting action is successful.
+        ext_fungible_token::mint_to(
             account_id.clone(),
-            charge_amount,
+            num_shares.into(),
             &self.token_contract,
             NO_DEPOSIT,
             MINT_AND_BURN_GAS,
         ).then(ext_self::on_mint_action(
             account_id,
-            charge_amount,
-            num_shares,
+            charge_amount.into(),
+            ticket_amount.into(),
+            num_shares.into(),
+            stake_amount.into(),
             &env::current_account_id(),
             NO_DEPOSIT,
             ON_MINT_AND_BURN_ACTION_GAS,
         ));
     }
 
-    pub(crate) fn inner_unstake(&mut self, amount: Balance) {
-        assert!(amount > 0, "Unstaking amount should be positive");
+    /// Unstakes `num_shares` "stake" shares held by the inner account of `account_id`.
+    pub(crate) fn internal_unstake_shares(&mut self, account_id: AccountId, num_shares: NumStakeShares) {
+        assert!(num_shares > 0, "Unstaking amount should be positive");
 
-        let account_id = env::predecessor_account_id();
         let mut account = self.internal_get_account(&account_id);
-
         assert!(
-            self.total_staked_balance > 0,
-            "The contract doesn't have staked balance"
+            account.stake_shares >= num_shares,
+            "Not enough staked balance to unstake"
         );
-        // Calculate the number of shares required to unstake the given amount.
-        // NOTE: The number of shares the account will pay is rounded up.
-        let num_shares = self.num_shares_from_staked_amount_rounded_up(amount);
         assert!(
-            num_shares > 0,
-            "Invariant violation. The calculated number of \"stake\" shares for unstaking should be positive"
+            env::block_timestamp() >= account.vote_unlock_time,
+            "The staked balance is locked to vote"
         );
+        account.stake_shares -= num_shares;
+        self.internal_unstake(&account_id, account, num_shares);
+    }
+
+    /// Burns `num_shares` receipts from the balance of `holder_id` and unstakes the "stake" shares
+    /// they hold to the inner account of `account_id`.
+    /// The callback resolves to the number of receipts that couldn't be redeemed.
+    pub(crate) fn internal_redeem(
+        &mut self,
+        holder_id: AccountId,
+        account_id: AccountId,
+        num_shares: NumStakeShares,
+    ) -> Promise {
+        assert!(num_shares > 0, "Unstaking amount should be positive");
         assert!(
-            account.stake_shares >= num_shares,
+            self.receipt_shares >= num_shares,
             "Not enough staked balance to unstake"
         );
 
+        // Unstake occurs only when burning action is successful.
+        ext_fungible_token::burn_from(
+            holder_id,
+            num_shares.into(),
+            &self.token_contract,
+            NO_DEPOSIT,
+            MINT_AND_BURN_GAS,
+        ).then(ext_self::on_burn_action(
+            account_id,
+            num_shares.into(),
+            &env::current_account_id(),
+            NO_DEPOSIT,
+            ON_MINT_AND_BURN_ACTION_GAS,
+        ))
+    }
+
+    /// Unstakes `num_shares` "stake" shares, already taken from their holder, to the unstaked
+    /// balance of `account`.
+    pub(crate) fn internal_unstake(
+        &mut self,
+        account_id: &AccountId,
+        mut account: Account,
+        num_shares: NumStakeShares,
+    ) {
+        assert!(
+            self.total_staked_balance > 0,
+            "The contract doesn't have staked balance"
+        );
         // Calculating the amount of tokens the account will receive by unstaking the corresponding
         // number of "stake" shares, rounding up.
         let receive_amount = self.staked_amount_from_num_shares_rounded_up(num_shares);
@@ -136,58 +272,37 @@ impl StakingContract {
             "Invariant violation. Calculated staked amount must be positive, because \"stake\" share price should be at least 1"
         );
 
-        // Reduce stake reward first, then reduce stake principal.
-        let stake_reward: Balance = self.internal_get_stake_reward(&account_id);
-
-        if stake_reward >= receive_amount {
-            account.stake_shares -= num_shares;
-            account.unstaked += receive_amount;
-            account.unstaked_available_epoch_height = env::epoch_height() + NUM_EPOCHS_TO_UNLOCK;
+        account.add_unstake_ticket(receive_amount, env::epoch_height() + NUM_EPOCHS_TO_UNLOCK);
+        self.internal_save_account(account_id, &account);
 
-            self.internal_save_account(&account_id, &account);
+        // The amount tokens that will be unstaked from the total to guarantee the "stake" share
+        // price never decreases. The difference between `receive_amount` and `unstake_amount` is
+        // paid from the allocated STAKE_SHARE_PRICE_GUARANTEE_FUND.
+        let unstake_amount = self.staked_amount_from_num_shares_rounded_down(num_shares);
 
-            // The amount tokens that will be unstaked from the total to guarantee the "stake" share
-            // price never decreases. The difference between `receive_amount` and `unstake_amount` is
-            // paid from the allocated STAKE_SHARE_PRICE_GUARANTEE_FUND.
-            let unstake_amount = self.staked_amount_from_num_shares_rounded_down(num_shares);
+        self.total_staked_balance -= unstake_amount;
+        self.total_stake_shares -= num_shares;
+        self.internal_restake();
 
-            self.total_staked_balance -= unstake_amount;
-            self.total_stake_shares -= num_shares;
+        env::log(
+            format!(
+                "@{} unstaking {}. Spent {} staking shares. Total {} unstaked balance and {} staking shares",
+                account_id, receive_amount, num_shares, account.unstaked, account.stake_shares
+            )
+                .as_bytes(),
+        );
+        env::log(
+            format!(
+                "Contract total staked balance is {}. Total number of shares {}",
+                self.total_staked_balance, self.total_stake_shares
+            )
+                .as_bytes(),
+        );
+    }
 
-            env::log(
-                format!(
-                    "@{} unstaking {}. Spent {} staking shares. Total {} unstaked balance and {} staking shares",
-                    account_id, receive_amount, num_shares, account.unstaked, account.stake_shares
-                )
-                    .as_bytes(),
-            );
-            env::log(
-                format!(
-                    "Contract total staked balance is {}. Total number of shares {}",
-                    self.total_staked_balance, self.total_stake_shares
-                )
-                    .as_bytes(),
-            );
-        } else {
-            let principal_reduced: Balance = receive_amount - stake_reward;
-            // The amount to burn bNEAR is same as the reduced staked principal.
-            // Unstake occurs only when burning action is successful.
-            ext_fungible_token::burn(
-                account_id.clone(),
-                principal_reduced,
-                &self.token_contract,
-                NO_DEPOSIT,
-                MINT_AND_BURN_GAS,
-            ).then(ext_self::on_burn_action(
-                account_id,
-                num_shares,
-                receive_amount,
-                principal_reduced,
-                &env::current_account_id(),
-                NO_DEPOSIT,
-                ON_MINT_AND_BURN_ACTION_GAS
-            ));
-        }
+    /// Asserts that the pool takes native NEAR deposits.
+    pub(crate) fn assert_native(&self) {
+        assert!(self.asset == Asset::Native, "The pool only takes deposits of its token");
     }
 
     /// Asserts that the method was called by the owner.
@@ -207,6 +322,69 @@ impl StakingContract {
             return false;
         }
         self.last_epoch_height = epoch_height;
+
+        // New total amount (both locked and unlocked balances).
+        // NOTE: The attached deposit is already included in the account balance, but it isn't
+        // accounted yet when `ping` is called from a payable method.
+        let total_balance =
+            env::account_locked_balance() + env::account_balance() - env::attached_deposit();
+
+        assert!(
+            total_balance >= self.last_total_balance,
+            "The new total balance should not be less than the old total balance"
+        );
+        // An announced fee increase applies to the rewards distributed from its epoch on.
+        if self
+            .announced_reward_fee
+            .as_ref()
+            .map_or(false, |announced| announced.effective_epoch_height <= epoch_height)
+        {
+            let announced = self.announced_reward_fee.take().unwrap();
+            self.reward_fee_fraction = announced.reward_fee_fraction;
+        }
+
+        // The NEAR a token pool receives isn't a reward of its token shares.
+        let total_reward = match self.asset {
+            Asset::Native => total_balance - self.last_total_balance,
+            Asset::Token { .. } => 0,
+        };
+        if total_reward > 0 {
+            // The validation fee that the fee beneficiary takes.
+            let owners_fee = self.reward_fee_fraction.multiply(total_reward);
+
+            // Distributing the remaining reward to the delegators first.
+            let remaining_reward = total_reward - owners_fee;
+            self.total_staked_balance += remaining_reward;
+
+            // Now buying "stake" shares for the fee beneficiary at the new share price.
+            let num_shares = self.num_shares_from_staked_amount_rounded_down(owners_fee);
+            if num_shares > 0 {
+                // Updating fee beneficiary's inner account
+                let fee_beneficiary_id = self.fee_beneficiary_id.clone();
+                let mut account = self.internal_get_account(&fee_beneficiary_id);
+                account.stake_shares += num_shares;
+                self.internal_save_account(&fee_beneficiary_id, &account);
+                // Increasing the total amount of "stake" shares.
+                self.total_stake_shares += num_shares;
+                self.accumulated_fee_shares += num_shares;
+            }
+            // Increasing the total staked balance by the owners fee, no matter whether the fee
+            // beneficiary received any shares or not.
+            self.total_staked_balance += owners_fee;
+
+            env::log(
+                format!(
+                    "Epoch {}: Contract received total rewards of {} tokens. New total staked balance is {}. Total number of shares {}",
+                    epoch_height, total_reward, self.total_staked_balance, self.total_stake_shares,
+                )
+                    .as_bytes(),
+            );
+            if num_shares > 0 {
+                env::log(format!("Total rewards fee is {} stake shares.", num_shares).as_bytes());
+            }
+        }
+
+        self.last_total_balance = total_balance;
         true
     }
 
@@ -286,13 +464,6 @@ impl StakingContract {
         self.accounts.get(account_id).unwrap_or_default()
     }
 
-    pub(crate) fn internal_get_stake_reward(&self, account_id: &AccountId) -> Balance {
-        let account = self.internal_get_account(account_id);
-        let staked_balance: Balance = self.staked_amount_from_num_shares_rounded_down(account.stake_shares);
-
-        staked_balance - account.stake_principal
-    }
-
     /// Inner method to save the given account for a given account ID.
     /// If the account balances are 0, the account is deleted instead to release storage.
     pub(crate) fn internal_save_account(&mut self, account_id: &AccountId, account: &Account) {
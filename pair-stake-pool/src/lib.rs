@@ -1,12 +1,12 @@
 use std::convert::TryInto;
 
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::UnorderedMap;
-use near_sdk::json_types::{Base58PublicKey, U128, ValidAccountId};
+use near_sdk::collections::{UnorderedMap, UnorderedSet};
+use near_sdk::json_types::{Base58PublicKey, U128, U64, ValidAccountId};
 use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::{
     env, near_bindgen, AccountId, Balance, EpochHeight, Promise, PublicKey,
-    ext_contract, PromiseResult, Gas, assert_one_yocto,
+    ext_contract, PromiseResult, Gas, assert_one_yocto, Timestamp,
 };
 use uint::construct_uint;
 
@@ -16,7 +16,10 @@ mod utils;
 mod views;
 mod token_receiver;
 
-use crate::utils::{ext_voting, ext_fungible_token, ext_self};
+#[cfg(test)]
+mod test_utils;
+
+use crate::utils::{ext_voting, ext_fungible_token, ext_self, ext_whitelist};
 
 /// The amount of gas given to complete `vote` call.
 const VOTE_GAS: Gas = 100_000_000_000_000;
@@ -30,10 +33,44 @@ const ON_MINT_AND_BURN_ACTION_GAS: Gas = 100_000_000_000_000;
 /// The amount of gas given to complete 'mint' and 'burn' call.
 const MINT_AND_BURN_GAS: Gas = 20_000_000_000_000;
 
+/// The amount of gas given to complete `ft_transfer` call of a withdrawal.
+const FT_TRANSFER_GAS: Gas = 10_000_000_000_000;
+
+/// The amount of gas given to complete internal `on_withdraw` call.
+const ON_WITHDRAW_GAS: Gas = 10_000_000_000_000;
+
+/// The amount of gas given to complete `lock_for_vote` call of the liquid token.
+const LOCK_FOR_VOTE_GAS: Gas = 10_000_000_000_000;
+
+/// The amount of gas given to complete internal `on_receipts_locked` call.
+const ON_RECEIPTS_LOCKED_GAS: Gas = 10_000_000_000_000;
+
+/// The amount of gas given to complete `is_whitelisted` call of the external whitelist.
+const IS_WHITELISTED_GAS: Gas = 10_000_000_000_000;
+
+/// The amount of gas given to complete internal `on_whitelist_check` call, which can stake.
+const ON_WHITELIST_CHECK_GAS: Gas = 150_000_000_000_000;
+
 /// The amount of yocto NEAR the contract dedicates to guarantee that the "share" price never
 /// decreases. It's used during rounding errors for share -> amount conversions.
 const STAKE_SHARE_PRICE_GUARANTEE_FUND: Balance = 1_000_000_000_000;
 
+/// The number of epochs required for the locked balance to become unlocked.
+/// NOTE: The actual number of epochs when the funds are unlocked is 3. But there is a corner case
+/// when the unstaking promise can arrive at the next epoch, while the inner state is already
+/// updated in the previous epoch. It will not unlock the funds for 4 epochs.
+const NUM_EPOCHS_TO_UNLOCK: EpochHeight = 4;
+
+/// The number of epochs between the announcement of a reward fee increase and its application.
+const REWARD_FEE_INCREASE_NOTICE_EPOCHS: EpochHeight = 4;
+
+/// The most the reward fee can be raised by a single announcement.
+const MAX_REWARD_FEE_INCREASE: RewardFeeFraction = RewardFeeFraction { numerator: 5, denominator: 100 };
+
+/// The maximum number of pending unstake tickets of an account. Once reached, a new unstake is
+/// merged into the latest ticket.
+const MAX_UNSTAKE_TICKETS: usize = 8;
+
 /// There is no deposit balance attached.
 const NO_DEPOSIT: Balance = 0;
 
@@ -48,12 +85,25 @@ construct_uint! {
 #[global_allocator]
 static ALLOC: near_sdk::wee_alloc::WeeAlloc = near_sdk::wee_alloc::WeeAlloc::INIT;
 
+/// Part of the unstaked balance of an account that can't be withdrawn before an epoch.
+#[derive(BorshDeserialize, BorshSerialize, Debug, PartialEq, Clone)]
+pub struct UnstakeTicket {
+    pub amount: Balance,
+    pub unlock_epoch_height: EpochHeight,
+}
+
 /// Inner account data of a delegate.
 #[derive(BorshDeserialize, BorshSerialize, Debug, PartialEq)]
 pub struct Account {
+    /// Unstaked balance, the tickets' included.
     pub unstaked: Balance,
-    pub unstaked_available_epoch_height: EpochHeight,
-    pub stake_principal: Balance,
+    /// "Stake" shares held by the account itself, i.e. the reward fee shares of the fee beneficiary.
+    /// Shares staked by the delegators are held by their receipts instead.
+    pub stake_shares: NumStakeShares,
+    /// Tickets of the recent unstakes, from the oldest to the latest.
+    pub unstake_tickets: Vec<UnstakeTicket>,
+    /// The "stake" shares can't be unstaked before this time, as they were counted to vote.
+    pub vote_unlock_time: Timestamp,
 }
 
 /// Represents an account structure readable by humans.
@@ -67,27 +117,115 @@ pub struct HumanReadableAccount {
     pub stake_reward: U128,
 }
 
+/// Represents an unstake ticket readable by humans.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct HumanReadableUnstakeTicket {
+    pub amount: U128,
+    pub unlock_epoch_height: EpochHeight,
+    pub can_withdraw: bool,
+}
+
 impl Default for Account {
     fn default() -> Self {
         Self {
             unstaked: 0,
-            unstaked_available_epoch_height: 0,
-            stake_principal: 0,
+            stake_shares: 0,
+            unstake_tickets: vec![],
+            vote_unlock_time: 0,
         }
     }
 }
 
+impl Account {
+    /// Returns the unstaked balance still locked by tickets at the given epoch.
+    pub fn locked_balance(&self, epoch_height: EpochHeight) -> Balance {
+        self.unstake_tickets
+            .iter()
+            .filter(|ticket| ticket.unlock_epoch_height > epoch_height)
+            .map(|ticket| ticket.amount)
+            .sum()
+    }
+
+    /// Returns the unstaked balance that can be withdrawn at the given epoch.
+    pub fn available_balance(&self, epoch_height: EpochHeight) -> Balance {
+        self.unstaked - self.locked_balance(epoch_height)
+    }
+
+    /// Adds `amount` to the unstaked balance, locked until `unlock_epoch_height`.
+    pub fn add_unstake_ticket(&mut self, amount: Balance, unlock_epoch_height: EpochHeight) {
+        self.unstaked += amount;
+        let full = self.unstake_tickets.len() >= MAX_UNSTAKE_TICKETS;
+        match self.unstake_tickets.last_mut() {
+            Some(latest) if full || latest.unlock_epoch_height == unlock_epoch_height => {
+                // Merging into the latest ticket only delays it, the tickets are in unlock order.
+                latest.amount += amount;
+                latest.unlock_epoch_height = unlock_epoch_height;
+            }
+            _ => self.unstake_tickets.push(UnstakeTicket { amount, unlock_epoch_height }),
+        }
+    }
+
+    /// Drops the tickets that matured at the given epoch.
+    pub fn sweep_unstake_tickets(&mut self, epoch_height: EpochHeight) {
+        self.unstake_tickets.retain(|ticket| ticket.unlock_epoch_height > epoch_height);
+    }
+
+    /// Takes `amount` from the unstaked balance, from the available balance first and then from the
+    /// latest tickets.
+    pub fn take_unstaked(&mut self, amount: Balance, epoch_height: EpochHeight) {
+        assert!(self.unstaked >= amount, "Not enough unstaked balance");
+        self.sweep_unstake_tickets(epoch_height);
+        let mut from_tickets = amount.saturating_sub(self.available_balance(epoch_height));
+        while from_tickets > 0 {
+            let latest = self.unstake_tickets.last_mut().unwrap();
+            let taken = std::cmp::min(latest.amount, from_tickets);
+            latest.amount -= taken;
+            from_tickets -= taken;
+            if latest.amount == 0 {
+                self.unstake_tickets.pop();
+            }
+        }
+        self.unstaked -= amount;
+    }
+}
+
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize)]
 pub struct StakingContract {
     pub owner_id: AccountId,
     pub stake_public_key: PublicKey,
+    /// Epoch of the last ping, the rewards up to it are distributed.
     pub last_epoch_height: EpochHeight,
+    /// Locked and unlocked balance of the pool at the last ping. Rewards are measured against it.
+    pub last_total_balance: Balance,
+    /// Balance that the "stake" shares are worth, the guarantee fund excluded.
     pub total_staked_balance: Balance,
+    /// Total number of "stake" shares: the ones held by the accounts, the `receipt_shares` and the
+    /// ones `new` seeds for the initial balance, which nobody holds.
+    pub total_stake_shares: NumStakeShares,
     pub reward_fee_fraction: RewardFeeFraction,
+    /// A reward fee increase that applies from a later epoch.
+    pub announced_reward_fee: Option<AnnouncedRewardFee>,
+    /// Account receiving the reward fee shares, the owner unless set otherwise.
+    pub fee_beneficiary_id: AccountId,
+    /// Reward fee shares minted since the initialization.
+    pub accumulated_fee_shares: NumStakeShares,
     pub accounts: UnorderedMap<AccountId, Account>,
     pub paused: bool,
+    /// Liquid staking token minted to the delegators as receipts of their stake.
+    /// One receipt is worth one "stake" share, whoever holds it.
     pub token_contract: AccountId,
+    /// "Stake" shares held by the receipts in circulation.
+    pub receipt_shares: NumStakeShares,
+    /// What the delegators deposit and withdraw.
+    pub asset: Asset,
+    /// Who can deposit the token of a token pool.
+    pub deposit_whitelist: DepositWhitelist,
+    /// Accounts allowed to deposit by the `Local` whitelist.
+    pub whitelisted_accounts: UnorderedSet<AccountId>,
+    /// Voting contract allowed to lock staked balances it counts as voting power.
+    pub vote_locker: Option<AccountId>,
 }
 
 impl Default for StakingContract {
@@ -96,6 +234,30 @@ impl Default for StakingContract {
     }
 }
 
+/// What the delegators deposit into the pool and get back on withdrawal.
+/// Only native NEAR is staked with the validator and earns its rewards. The tokens of a token pool
+/// stay in the pool, so its "stake" shares are always worth one token each.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum Asset {
+    /// A NEP-141 token, deposited with `ft_transfer_call` and withdrawn with `ft_transfer`.
+    Token { token_id: AccountId },
+    /// NEAR attached to `deposit` and `deposit_and_stake`.
+    Native,
+}
+
+/// Who can deposit the token of a token pool. Redeeming receipts is never restricted.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum DepositWhitelist {
+    /// Any account.
+    Open,
+    /// The accounts of the pool's own list.
+    Local,
+    /// The accounts approved by `is_whitelisted(account_id)` of an external whitelist contract.
+    External { whitelist_account_id: AccountId },
+}
+
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
 #[serde(crate = "near_sdk::serde")]
 pub struct RewardFeeFraction {
@@ -115,6 +277,23 @@ impl RewardFeeFraction {
     pub fn multiply(&self, value: Balance) -> Balance {
         (U256::from(self.numerator) * U256::from(value) / U256::from(self.denominator)).as_u128()
     }
+
+    /// Returns true if the fee is higher than `other` raised by `margin`.
+    pub fn exceeds(&self, other: &RewardFeeFraction, margin: &RewardFeeFraction) -> bool {
+        // n / d > (on * md + mn * od) / (od * md)
+        let (n, d) = (self.numerator as u128, self.denominator as u128);
+        let (on, od) = (other.numerator as u128, other.denominator as u128);
+        let (mn, md) = (margin.numerator as u128, margin.denominator as u128);
+        n * od * md > (on * md + mn * od) * d
+    }
+}
+
+/// A reward fee increase announced ahead of its application.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AnnouncedRewardFee {
+    pub reward_fee_fraction: RewardFeeFraction,
+    pub effective_epoch_height: EpochHeight,
 }
 
 #[near_bindgen]
@@ -125,6 +304,7 @@ impl StakingContract {
         stake_public_key: Base58PublicKey,
         reward_fee_fraction: RewardFeeFraction,
         token_contract: ValidAccountId,
+        asset: Asset,
     ) -> Self {
         assert!(!env::state_exists(), "Already initialized");
         reward_fee_fraction.assert_valid();
@@ -133,39 +313,100 @@ impl StakingContract {
             "The owner account ID is invalid"
         );
 
+        let account_balance = env::account_balance();
+        assert!(
+            account_balance > STAKE_SHARE_PRICE_GUARANTEE_FUND,
+            "The pool needs a balance above the stake share price guarantee fund"
+        );
+        assert_eq!(
+            env::account_locked_balance(),
+            0,
+            "The staking pool shouldn't be staking at the initialization"
+        );
+        // The initial balance of the pool is staked for nobody at a share price of 1, except for
+        // the guarantee fund. A token pool doesn't stake its NEAR, it seeds the price of 1 token
+        // per share with shares of the guarantee fund's size instead.
+        let total_staked_balance = match &asset {
+            Asset::Native => account_balance - STAKE_SHARE_PRICE_GUARANTEE_FUND,
+            Asset::Token { .. } => STAKE_SHARE_PRICE_GUARANTEE_FUND,
+        };
+
         Self {
+            fee_beneficiary_id: owner_id.clone(),
             owner_id,
             stake_public_key: stake_public_key.into(),
             last_epoch_height: env::epoch_height(),
-            total_staked_balance: 0,
+            last_total_balance: account_balance,
+            total_staked_balance,
+            total_stake_shares: total_staked_balance,
             reward_fee_fraction,
+            announced_reward_fee: None,
+            accumulated_fee_shares: 0,
             accounts: UnorderedMap::new(b"u".to_vec()),
             paused: false,
             token_contract: token_contract.into(),
-        };
+            receipt_shares: 0,
+            asset,
+            deposit_whitelist: DepositWhitelist::Open,
+            whitelisted_accounts: UnorderedSet::new(b"w".to_vec()),
+            vote_locker: None,
+        }
+    }
+
+    /// Distributes rewards and restakes if needed.
+    pub fn ping(&mut self) {
+        if self.internal_ping() {
+            self.internal_restake();
+        }
+    }
+
+    /// Deposits the attached amount into the inner account of the predecessor.
+    /// Only for pools of native NEAR, token pools take deposits through `ft_transfer_call`.
+    #[payable]
+    pub fn deposit(&mut self) {
+        self.assert_native();
+        let account_id = env::predecessor_account_id();
+        self.internal_deposit(&account_id, env::attached_deposit());
+    }
+
+    /// Deposits the attached amount into the inner account of the predecessor and stakes it.
+    /// Only for pools of native NEAR, token pools take deposits through `ft_transfer_call`.
+    #[payable]
+    pub fn deposit_and_stake(&mut self) {
+        self.assert_native();
+        let account_id = env::predecessor_account_id();
+        self.internal_deposit_and_stake(&account_id, env::attached_deposit());
     }
 
-    /// Withdraws the entire unstaked balance from the predecessor account.
-    /// It's only allowed if the `unstake` action was not performed in the four most recent epochs.
+    /// Withdraws the entire available unstaked balance from the predecessor account.
+    /// The balance of unstake tickets is available once they mature.
     #[payable]
     pub fn withdraw_all(&mut self) {
         assert_one_yocto();
-        self.internal_ping();
+        let need_to_restake = self.internal_ping();
 
         let account_id = env::predecessor_account_id();
         let account = self.internal_get_account(&account_id);
-        self.internal_withdraw(account.unstaked);
+        self.internal_withdraw(account.available_balance(env::epoch_height()));
+
+        if need_to_restake {
+            self.internal_restake();
+        }
     }
 
     /// Withdraws the non staked balance for given account.
-    /// It's only allowed if the `unstake` action was not performed in the four most recent epochs.
+    /// The balance of unstake tickets is available once they mature.
     #[payable]
     pub fn withdraw(&mut self, amount: U128) {
         assert_one_yocto();
-        self.internal_ping();
+        let need_to_restake = self.internal_ping();
 
         let amount: Balance = amount.into();
         self.internal_withdraw(amount);
+
+        if need_to_restake {
+            self.internal_restake();
+        }
     }
 
     /// Stakes the given amount from the inner account of the predecessor.
@@ -176,21 +417,23 @@ impl StakingContract {
         self.internal_ping();
 
         let amount: Balance = amount.into();
-        self.internal_stake(amount);
+        self.internal_stake(env::predecessor_account_id(), amount);
     }
 
     /// Stakes all available unstaked balance from the inner account of the predecessor.
     #[payable]
     pub fn stake_all(&mut self) {
         assert_one_yocto();
+        self.internal_ping();
 
         let account_id = env::predecessor_account_id();
         let account = self.internal_get_account(&account_id);
-        self.internal_stake(account.unstaked);
+        self.internal_stake(account_id, account.unstaked);
     }
 
-    /// Unstakes all staked balance from the inner account of the predecessor.
-    /// The new total unstaked balance will be available for withdrawal in four epochs.
+    /// Unstakes all "stake" shares held by the inner account of the predecessor, i.e. the reward
+    /// fee shares of the fee beneficiary. Receipts are unstaked with `unstake`.
+    /// The unstaked balance will be available for withdrawal in four epochs.
     #[payable]
     pub fn unstake_all(&mut self) {
         assert_one_yocto();
@@ -198,31 +441,673 @@ impl StakingContract {
 
         let account_id = env::predecessor_account_id();
         let account = self.internal_get_account(&account_id);
-        let amount = self.staked_amount_from_num_shares_rounded_down(account.stake_shares);
-        self.inner_unstake(amount);
+        self.internal_unstake_shares(account_id, account.stake_shares);
     }
 
-    /// Unstakes the given amount from the inner account of the predecessor.
-    /// The inner account should have enough staked balance.
-    /// The new total unstaked balance will be available for withdrawal in four epochs.
+    /// Redeems the given amount of receipts held by the predecessor, who doesn't need to be the
+    /// account that staked them. The receipts are burned and the staked balance they are worth is
+    /// unstaked to the inner account of the predecessor.
+    /// The unstaked balance will be available for withdrawal in four epochs.
     #[payable]
     pub fn unstake(&mut self, amount: U128) {
         assert_one_yocto();
         self.internal_ping();
 
-        let amount: Balance = amount.into();
-        self.inner_unstake(amount);
+        let account_id = env::predecessor_account_id();
+        self.internal_redeem(account_id.clone(), account_id, amount.into());
     }
 
-    /// Unstakes all stake reward from the inner account of the predecessor.
-    /// The new total unstaked balance will be available for withdrawal in four epochs.
-    #[payable]
-    pub fn unstake_reward(&mut self) {
-        assert_one_yocto();
-        self.internal_ping();
+    /// Vote locker's method.
+    /// Locks the "stake" shares held by the given account and its receipts until `unlock_time`,
+    /// or later if they are already locked for longer, and resolves to the staked balance they are
+    /// worth. The voting contract counts it as voting power, so it can't be unstaked or transferred
+    /// and counted again elsewhere. The receipts are locked by the liquid token, which needs the
+    /// pool as its vote locker.
+    pub fn lock_for_vote(&mut self, account_id: ValidAccountId, unlock_time: U64) -> Promise {
+        assert_eq!(
+            Some(env::predecessor_account_id()),
+            self.vote_locker,
+            "Can only be called by the vote locker"
+        );
+        let account_id: AccountId = account_id.into();
+        // The "stake" shares are locked once the receipts are, so a failed receipt lock leaves
+        // nothing locked.
+        ext_fungible_token::lock_for_vote(
+            account_id.clone(),
+            unlock_time,
+            &self.token_contract,
+            NO_DEPOSIT,
+            LOCK_FOR_VOTE_GAS,
+        ).then(ext_self::on_receipts_locked(
+            account_id,
+            unlock_time,
+            &env::current_account_id(),
+            NO_DEPOSIT,
+            ON_RECEIPTS_LOCKED_GAS,
+        ))
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
 
-        let account_id = env::predecessor_account_id();
-        let amount: Balance = self.internal_get_stake_reward(&account_id);
-        self.inner_unstake(amount);
+    use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+    use near_sdk::{testing_env_with_promise_results, PromiseOrValue};
+    use quickcheck_macros::quickcheck;
+
+    use super::*;
+    use crate::test_utils::*;
+
+    fn reward_fee(numerator: u32, denominator: u32) -> RewardFeeFraction {
+        RewardFeeFraction { numerator, denominator }
+    }
+
+    /// Asserts the balances are equal up to share rounding.
+    fn assert_close(left: Balance, right: Balance) {
+        assert!(
+            std::cmp::max(left, right) - std::cmp::min(left, right) <= 2,
+            "{} and {} differ by more than rounding",
+            left,
+            right
+        );
+    }
+
+    #[test]
+    fn test_ping_in_same_epoch() {
+        let mut emulator = Emulator::new(ntoy(30), reward_fee(10, 100));
+        emulator.locked_amount += ntoy(1);
+        emulator.update_context(OWNER, 0);
+        assert!(!emulator.contract.internal_ping());
+        assert_eq!(emulator.contract.last_total_balance, ntoy(30));
+        assert_eq!(emulator.contract.total_staked_balance, ntoy(30) - STAKE_SHARE_PRICE_GUARANTEE_FUND);
+    }
+
+    #[test]
+    fn test_rewards_over_multiple_epochs() {
+        let mut emulator = Emulator::new(ntoy(30), reward_fee(10, 100));
+        emulator.deposit("alice", ntoy(100));
+        let receipts = emulator.stake("alice", ntoy(100));
+        assert_eq!(emulator.receipt_value(receipts), ntoy(100));
+        assert_eq!(emulator.contract.receipt_shares, receipts);
+        assert_eq!(emulator.contract.last_total_balance, ntoy(130));
+
+        // The owner takes 10% of the reward in shares, the rest goes to the delegators.
+        emulator.skip_epoch(ntoy(13));
+        let owner_balance = emulator.staked_balance(OWNER);
+        assert_close(owner_balance, ntoy(13) / 10);
+        // Fee shares are all reward.
+        assert_eq!(emulator.contract.get_account_stake_reward(OWNER.to_string()).0, owner_balance);
+        let alice_reward = emulator.receipt_value(receipts) - ntoy(100);
+        let expected = (U256::from(ntoy(13) - ntoy(13) / 10) * U256::from(ntoy(100))
+            / U256::from(ntoy(130) - STAKE_SHARE_PRICE_GUARANTEE_FUND))
+        .as_u128();
+        assert_close(alice_reward, expected);
+
+        let mut total_reward = ntoy(13);
+        for reward in [0, ntoy(7), 1, ntoy(20)].iter() {
+            let price = emulator.share_price();
+            let owner_balance = emulator.staked_balance(OWNER);
+            let alice_balance = emulator.receipt_value(receipts);
+            let total_staked_balance = emulator.contract.total_staked_balance;
+
+            emulator.skip_epoch(*reward);
+            total_reward += reward;
+
+            // The whole reward is staked and nobody loses value.
+            assert_eq!(emulator.contract.total_staked_balance, total_staked_balance + reward);
+            assert_eq!(emulator.contract.last_total_balance, ntoy(130) + total_reward);
+            assert!(emulator.share_price() >= price);
+            assert!(emulator.staked_balance(OWNER) >= owner_balance);
+            assert!(emulator.receipt_value(receipts) >= alice_balance);
+        }
+        assert!(emulator.share_price() > ntoy(1));
+        assert!(
+            emulator.staked_balance(OWNER) + emulator.receipt_value(receipts)
+                <= emulator.contract.total_staked_balance
+        );
+    }
+
+    #[test]
+    fn test_rewards_without_fee() {
+        let mut emulator = Emulator::new(ntoy(30), reward_fee(0, 100));
+        emulator.deposit("alice", ntoy(100));
+        let receipts = emulator.stake("alice", ntoy(100));
+
+        emulator.skip_epoch(ntoy(13));
+        // Nobody holds shares directly, alice staked everything for receipts.
+        assert_eq!(emulator.contract.get_number_of_accounts(), 0);
+        assert_eq!(emulator.contract.total_stake_shares, ntoy(130) - STAKE_SHARE_PRICE_GUARANTEE_FUND);
+        assert!(emulator.receipt_value(receipts) > ntoy(109));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_redeem_receipts_of_another_account() {
+        let mut emulator = Emulator::new(ntoy(30), reward_fee(10, 100));
+        emulator.deposit("alice", ntoy(100));
+        let receipts = emulator.stake("alice", ntoy(100));
+        emulator.skip_epoch(ntoy(13));
+
+        // Bob got the receipts of alice and redeems them at the current share price.
+        let price = emulator.share_price();
+        let value = emulator.receipt_value(receipts);
+        emulator.redeem("bob", receipts);
+        let bob = emulator.contract.get_account("bob".to_string());
+        assert_close(bob.unstaked_balance.0, value);
+        assert!(!bob.can_withdraw);
+        assert_eq!(emulator.contract.get_account_total_balance("alice".to_string()).0, 0);
+        assert_eq!(emulator.contract.receipt_shares, 0);
+        assert!(emulator.share_price() >= price);
+
+        emulator.epoch_height += NUM_EPOCHS_TO_UNLOCK;
+        emulator.update_context("bob", 0);
+        assert!(emulator.contract.is_account_unstaked_balance_available("bob".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough staked balance to unstake")]
+    fn test_redeem_more_than_receipts() {
+        let mut emulator = Emulator::new(ntoy(30), reward_fee(10, 100));
+        emulator.deposit("alice", ntoy(100));
+        let receipts = emulator.stake("alice", ntoy(100));
+        emulator.redeem("bob", receipts + 1);
+    }
+
+    fn unlock_epochs(emulator: &Emulator, account_id: &str) -> Vec<EpochHeight> {
+        emulator
+            .contract
+            .get_account_unstake_tickets(account_id.to_string())
+            .iter()
+            .map(|ticket| ticket.unlock_epoch_height)
+            .collect()
+    }
+
+    #[test]
+    fn test_unstake_tickets_mature_separately() {
+        let mut emulator = Emulator::new(ntoy(30), reward_fee(10, 100));
+        emulator.deposit("alice", ntoy(100));
+        let receipts = emulator.stake("alice", ntoy(100));
+        emulator.redeem("alice", receipts / 2);
+        let first = emulator.contract.get_account_unstaked_balance("alice".to_string()).0;
+        emulator.skip_epoch(0);
+        emulator.skip_epoch(0);
+        emulator.redeem("alice", receipts / 4);
+        assert_eq!(unlock_epochs(&emulator, "alice"), vec![4, 6]);
+
+        // Only the first ticket matured, the withdrawal sweeps it.
+        emulator.epoch_height = 4;
+        emulator.update_context("alice", 0);
+        let account = emulator.contract.internal_get_account(&"alice".to_string());
+        assert_eq!(account.available_balance(4), first);
+        emulator.withdraw("alice", first);
+        assert_eq!(unlock_epochs(&emulator, "alice"), vec![6]);
+        assert!(!emulator.contract.is_account_unstaked_balance_available("alice".to_string()));
+
+        emulator.epoch_height = 6;
+        emulator.update_context("alice", 0);
+        assert!(emulator.contract.get_account_unstake_tickets("alice".to_string())[0].can_withdraw);
+        let second = emulator.contract.get_account_unstaked_balance("alice".to_string()).0;
+        emulator.withdraw("alice", second);
+        assert_eq!(emulator.contract.get_account_total_balance("alice".to_string()).0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "The unstaked balance is not yet available due to unstaking delay")]
+    fn test_withdraw_before_ticket_matures() {
+        let mut emulator = Emulator::new(ntoy(30), reward_fee(10, 100));
+        emulator.deposit("alice", ntoy(100));
+        let receipts = emulator.stake("alice", ntoy(50));
+        emulator.redeem("alice", receipts);
+        emulator.epoch_height = NUM_EPOCHS_TO_UNLOCK - 1;
+        emulator.withdraw("alice", ntoy(51));
+    }
+
+    #[test]
+    fn test_unstake_tickets_are_capped() {
+        let mut emulator = Emulator::new(ntoy(30), reward_fee(10, 100));
+        emulator.deposit("alice", ntoy(100));
+        let receipts = emulator.stake("alice", ntoy(100));
+        for _ in 0..MAX_UNSTAKE_TICKETS + 2 {
+            emulator.redeem("alice", receipts / 20);
+            emulator.skip_epoch(0);
+        }
+        let account = emulator.contract.internal_get_account(&"alice".to_string());
+        assert_eq!(account.unstake_tickets.len(), MAX_UNSTAKE_TICKETS);
+        assert_eq!(account.locked_balance(0), account.unstaked);
+        // The latest ticket took the unstakes past the cap and unlocks with the last of them.
+        let last_epoch = (MAX_UNSTAKE_TICKETS + 1) as EpochHeight;
+        assert_eq!(account.unstake_tickets.last().unwrap().unlock_epoch_height, last_epoch + 4);
+    }
+
+    #[test]
+    fn test_stake_takes_available_balance_before_tickets() {
+        let mut emulator = Emulator::new(ntoy(30), reward_fee(10, 100));
+        emulator.deposit("alice", ntoy(100));
+        let receipts = emulator.stake("alice", ntoy(60));
+        emulator.redeem("alice", receipts);
+        emulator.skip_epoch(0);
+        let receipts = emulator.stake("alice", ntoy(30));
+        emulator.redeem("alice", receipts);
+        assert_eq!(unlock_epochs(&emulator, "alice"), vec![4, 5]);
+
+        // Staking 50 spends the available 10, all of the latest ticket and 10 of the first one.
+        emulator.skip_epoch(0);
+        emulator.stake("alice", ntoy(50));
+        let account = emulator.contract.internal_get_account(&"alice".to_string());
+        assert_eq!(account.unstaked, ntoy(50));
+        assert_eq!(account.available_balance(2), 0);
+        assert_eq!(account.unstake_tickets, vec![UnstakeTicket { amount: ntoy(50), unlock_epoch_height: 4 }]);
+    }
+
+    #[test]
+    fn test_failed_mint_gives_stake_back() {
+        let mut emulator = Emulator::new(ntoy(30), reward_fee(10, 100));
+        emulator.deposit("alice", ntoy(100));
+        let receipts = emulator.stake("alice", ntoy(60));
+        emulator.redeem("alice", receipts);
+        emulator.skip_epoch(0);
+        let before = emulator.contract.internal_get_account(&"alice".to_string());
+        let price = emulator.share_price_fraction();
+
+        // Staking 80 spends the available 40 and 40 of the ticket, which is still unstaking after
+        // the mint fails.
+        emulator.stake_with_mint_result("alice", ntoy(80), PromiseResult::Failed);
+        let account = emulator.contract.internal_get_account(&"alice".to_string());
+        assert_eq!(account.unstaked, before.unstaked);
+        assert_eq!(account.available_balance(1), ntoy(40));
+        assert_eq!(unlock_epochs(&emulator, "alice"), vec![4, 5]);
+        assert_eq!(emulator.share_price_fraction(), price);
+        assert_eq!(emulator.contract.receipt_shares, 0);
+        emulator.assert_invariants(price);
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough unstaked balance to stake")]
+    fn test_stake_pending_mint_again() {
+        let mut emulator = Emulator::new(ntoy(30), reward_fee(10, 100));
+        emulator.deposit("alice", ntoy(100));
+        emulator.update_context("alice", 0);
+        emulator.contract.internal_stake("alice".to_string(), ntoy(100));
+        emulator.contract.internal_stake("alice".to_string(), ntoy(100));
+    }
+
+    #[test]
+    fn test_new_seeds_guarantee_fund() {
+        let emulator = Emulator::new(ntoy(30), reward_fee(10, 100));
+        assert_eq!(emulator.contract.last_total_balance, ntoy(30));
+        assert_eq!(emulator.contract.total_staked_balance, ntoy(30) - STAKE_SHARE_PRICE_GUARANTEE_FUND);
+        assert_eq!(emulator.contract.total_stake_shares, emulator.contract.total_staked_balance);
+        assert_eq!(emulator.share_price(), ntoy(1));
+        emulator.assert_invariants(emulator.share_price_fraction());
+    }
+
+    #[test]
+    #[should_panic(expected = "The pool needs a balance above the stake share price guarantee fund")]
+    fn test_new_without_guarantee_fund() {
+        Emulator::new(STAKE_SHARE_PRICE_GUARANTEE_FUND, reward_fee(10, 100));
+    }
+
+    #[test]
+    fn test_share_price_never_decreases() {
+        let mut emulator = Emulator::new(ntoy(30), reward_fee(7, 100));
+        let mut price = emulator.share_price_fraction();
+        let mut receipts = 0;
+        // Odd amounts and rewards make every conversion round.
+        for round in 1..=20u128 {
+            emulator.deposit("alice", ntoy(round) + 7 * round);
+            receipts += emulator.stake("alice", ntoy(round) / 3 + round);
+            emulator.assert_invariants(price);
+            price = emulator.share_price_fraction();
+
+            emulator.deposit("bob", 3 * round + 1);
+            emulator.stake("bob", 3 * round + 1);
+            emulator.assert_invariants(price);
+            price = emulator.share_price_fraction();
+
+            emulator.skip_epoch(ntoy(round) / 7 + round * 13);
+            emulator.assert_invariants(price);
+            price = emulator.share_price_fraction();
+
+            let redeemed = receipts / (round + 2) + 1;
+            emulator.redeem("alice", redeemed);
+            receipts -= redeemed;
+            emulator.assert_invariants(price);
+            price = emulator.share_price_fraction();
+
+            if round % 5 == 0 {
+                emulator.update_context(OWNER, 0);
+                let fee_shares = emulator.contract.internal_get_account(&OWNER.to_string()).stake_shares;
+                emulator.contract.internal_unstake_shares(OWNER.to_string(), fee_shares);
+                emulator.assert_invariants(price);
+                price = emulator.share_price_fraction();
+
+                emulator.update_context("alice", 0);
+                let available = emulator
+                    .contract
+                    .internal_get_account(&"alice".to_string())
+                    .available_balance(emulator.epoch_height);
+                emulator.withdraw("alice", available);
+                emulator.assert_invariants(price);
+                price = emulator.share_price_fraction();
+            }
+        }
+        assert!(emulator.share_price() > ntoy(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "The new total balance should not be less than the old total balance")]
+    fn test_ping_with_decreased_balance() {
+        let mut emulator = Emulator::new(ntoy(30), reward_fee(10, 100));
+        emulator.amount -= ntoy(1);
+        emulator.skip_epoch(0);
+    }
+
+    #[test]
+    fn test_fee_shares_go_to_beneficiary() {
+        let mut emulator = Emulator::new(ntoy(30), reward_fee(10, 100));
+        emulator.deposit("alice", ntoy(100));
+        emulator.stake("alice", ntoy(100));
+        emulator.update_context(OWNER, 0);
+        emulator.contract.set_fee_beneficiary(account("treasury"));
+        assert_eq!(emulator.contract.get_fee_beneficiary_id(), "treasury".to_string());
+
+        emulator.skip_epoch(ntoy(13));
+        assert_eq!(emulator.staked_balance(OWNER), 0);
+        assert_close(emulator.staked_balance("treasury"), ntoy(13) / 10);
+        // Fee shares are all reward.
+        let stake_reward = emulator.contract.get_account_stake_reward("treasury".to_string()).0;
+        assert_eq!(stake_reward, emulator.staked_balance("treasury"));
+        let fee_shares = emulator.contract.internal_get_account(&"treasury".to_string()).stake_shares;
+        assert_eq!(emulator.contract.get_accumulated_fee_shares().0, fee_shares);
+
+        // Claiming the fee shares doesn't change the accumulated ones.
+        emulator.update_context("treasury", 0);
+        emulator.contract.internal_unstake_shares("treasury".to_string(), fee_shares);
+        assert_eq!(emulator.contract.get_accumulated_fee_shares().0, fee_shares);
+    }
+
+    /// The owner gets fee shares and the voting contract "gov" locks them until 100, as well as
+    /// the receipts and later fee shares of alice.
+    fn emulator_with_fee_shares_locked_to_vote() -> Emulator {
+        let mut emulator = Emulator::new(ntoy(30), reward_fee(10, 100));
+        emulator.deposit("alice", ntoy(100));
+        let receipts = emulator.stake("alice", ntoy(100));
+        emulator.skip_epoch(ntoy(13));
+        let staked_balance = emulator.staked_balance(OWNER);
+        assert!(staked_balance > 0);
+
+        emulator.update_context(OWNER, 0);
+        emulator.contract.set_vote_locker(Some(account("gov")));
+        assert_eq!(emulator.lock_for_vote(OWNER, 100, 0), staked_balance);
+        // The receipts are valued with the "stake" shares, the liquid token locks them.
+        assert_eq!(emulator.lock_for_vote("alice", 100, receipts), emulator.receipt_value(receipts));
+        emulator.update_context(OWNER, 0);
+        emulator.contract.set_fee_beneficiary(account("alice"));
+        emulator.skip_epoch(ntoy(1));
+        let fee_shares = emulator.contract.internal_get_account(&"alice".to_string()).stake_shares;
+        assert_eq!(emulator.lock_for_vote("alice", 100, receipts), emulator.receipt_value(receipts + fee_shares));
+        emulator
+    }
+
+    #[test]
+    #[should_panic(expected = "The staked balance is locked to vote")]
+    fn test_vote_lock_blocks_unstake() {
+        let mut emulator = emulator_with_fee_shares_locked_to_vote();
+        emulator.block_timestamp = 99;
+        emulator.update_context(OWNER, 1);
+        emulator.contract.unstake_all();
+    }
+
+    #[test]
+    fn test_vote_lock_expires() {
+        let mut emulator = emulator_with_fee_shares_locked_to_vote();
+        emulator.block_timestamp = 100;
+        emulator.update_context(OWNER, 1);
+        emulator.contract.unstake_all();
+        assert_eq!(emulator.staked_balance(OWNER), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Locking the receipts to vote failed")]
+    fn test_lock_for_vote_without_receipt_lock() {
+        let mut emulator = emulator_with_fee_shares_locked_to_vote();
+        testing_env_with_promise_results(emulator.context(POOL, 0), PromiseResult::Failed);
+        emulator.contract.on_receipts_locked(OWNER.to_string(), U64(200));
+    }
+
+    #[test]
+    fn test_shares_locked_with_receipts() {
+        let mut emulator = Emulator::new(ntoy(30), reward_fee(10, 100));
+        emulator.deposit("alice", ntoy(100));
+        emulator.stake("alice", ntoy(100));
+        emulator.skip_epoch(ntoy(13));
+        emulator.update_context(OWNER, 0);
+        emulator.contract.set_vote_locker(Some(account("gov")));
+
+        // Nothing is locked until the liquid token locked the receipts.
+        emulator.update_context("gov", 0);
+        emulator.contract.lock_for_vote(account(OWNER), U64(100));
+        assert_eq!(emulator.contract.internal_get_account(&OWNER.to_string()).vote_unlock_time, 0);
+        emulator.lock_for_vote(OWNER, 100, 0);
+        assert_eq!(emulator.contract.internal_get_account(&OWNER.to_string()).vote_unlock_time, 100);
+    }
+
+    #[test]
+    #[should_panic(expected = "Can only be called by the vote locker")]
+    fn test_lock_for_vote_by_other_account() {
+        let mut emulator = Emulator::new(ntoy(30), reward_fee(10, 100));
+        emulator.update_context(OWNER, 0);
+        emulator.contract.lock_for_vote(account(OWNER), U64(100));
+    }
+
+    #[test]
+    fn test_fee_increase_applies_after_notice() {
+        let mut emulator = Emulator::new(ntoy(30), reward_fee(10, 100));
+        emulator.update_context(OWNER, 0);
+        emulator.contract.update_reward_fee_fraction(reward_fee(15, 100));
+        let announced = emulator.contract.get_announced_reward_fee().unwrap();
+        assert_eq!(announced.effective_epoch_height, REWARD_FEE_INCREASE_NOTICE_EPOCHS);
+
+        for _ in 1..REWARD_FEE_INCREASE_NOTICE_EPOCHS {
+            emulator.skip_epoch(ntoy(1));
+            assert_eq!(emulator.contract.get_reward_fee_fraction().numerator, 10);
+        }
+        let fee_shares = emulator.contract.accumulated_fee_shares;
+        emulator.skip_epoch(ntoy(1));
+        assert_eq!(emulator.contract.get_reward_fee_fraction().numerator, 15);
+        assert!(emulator.contract.get_announced_reward_fee().is_none());
+        let minted = emulator.contract.accumulated_fee_shares - fee_shares;
+        assert_close(emulator.contract.staked_amount_from_num_shares_rounded_down(minted), ntoy(15) / 100);
+
+        // A decrease applies right away and cancels the announced increase.
+        emulator.update_context(OWNER, 0);
+        emulator.contract.update_reward_fee_fraction(reward_fee(20, 100));
+        emulator.contract.update_reward_fee_fraction(reward_fee(1, 10));
+        assert_eq!(emulator.contract.get_reward_fee_fraction().numerator, 1);
+        assert!(emulator.contract.get_announced_reward_fee().is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "The reward fee can't be raised by more than 5/100 at once")]
+    fn test_fee_increase_above_cap() {
+        let mut emulator = Emulator::new(ntoy(30), reward_fee(10, 100));
+        emulator.update_context(OWNER, 0);
+        emulator.contract.update_reward_fee_fraction(reward_fee(151, 1000));
+    }
+
+    fn token_pool() -> Emulator {
+        let asset = Asset::Token { token_id: DEPOSIT_TOKEN.to_string() };
+        Emulator::with_asset(ntoy(30), reward_fee(10, 100), asset)
+    }
+
+    /// Sends `amount` of the deposit token to the pool from `sender_id`, returns the refund if the
+    /// pool answered right away.
+    fn transfer_deposit(emulator: &mut Emulator, sender_id: &str, amount: Balance) -> Option<Balance> {
+        emulator.update_context(DEPOSIT_TOKEN, 0);
+        match emulator.contract.ft_on_transfer(account(sender_id), amount.into(), String::new()) {
+            PromiseOrValue::Value(refund) => Some(refund.0),
+            PromiseOrValue::Promise(_) => None,
+        }
+    }
+
+    #[test]
+    fn test_local_whitelist_refunds_other_accounts() {
+        let mut emulator = token_pool();
+        emulator.update_context(OWNER, 0);
+        emulator.contract.set_deposit_whitelist(DepositWhitelist::Local);
+        emulator.contract.add_whitelisted_accounts(vec![account("alice"), account("bob")]);
+        emulator.contract.remove_whitelisted_accounts(vec![account("bob")]);
+        assert_eq!(emulator.contract.get_whitelisted_accounts(0, 10), vec!["alice".to_string()]);
+
+        assert_eq!(transfer_deposit(&mut emulator, "bob", ntoy(10)), Some(ntoy(10)));
+        assert_eq!(emulator.contract.get_account_unstaked_balance("bob".to_string()).0, 0);
+        assert_eq!(transfer_deposit(&mut emulator, "alice", ntoy(10)), Some(0));
+        assert_eq!(emulator.contract.get_account_unstaked_balance("alice".to_string()).0, ntoy(10));
+    }
+
+    #[test]
+    fn test_external_whitelist_answers() {
+        let mut emulator = token_pool();
+        emulator.update_context(OWNER, 0);
+        let deposit_whitelist = DepositWhitelist::External { whitelist_account_id: "whitelist".to_string() };
+        emulator.contract.set_deposit_whitelist(deposit_whitelist.clone());
+        assert!(emulator.contract.get_deposit_whitelist() == deposit_whitelist);
+
+        // The pool asks the whitelist before taking the deposit.
+        assert_eq!(transfer_deposit(&mut emulator, "alice", ntoy(10)), None);
+        assert_eq!(emulator.contract.get_account_unstaked_balance("alice".to_string()).0, 0);
+
+        let answers = vec![
+            (PromiseResult::Successful(b"false".to_vec()), ntoy(10)),
+            (PromiseResult::Failed, ntoy(10)),
+            (PromiseResult::Successful(b"true".to_vec()), 0),
+        ];
+        for (answer, refund) in answers {
+            testing_env_with_promise_results(emulator.context(POOL, 0), answer);
+            let result = emulator.contract.on_whitelist_check("alice".to_string(), ntoy(10).into(), false);
+            assert_eq!(result.0, refund);
+        }
+        assert_eq!(emulator.contract.get_account_unstaked_balance("alice".to_string()).0, ntoy(10));
+    }
+
+    #[test]
+    fn test_token_pool_keeps_near_rewards_out() {
+        let mut emulator = token_pool();
+        assert_eq!(emulator.share_price_fraction(), (STAKE_SHARE_PRICE_GUARANTEE_FUND, STAKE_SHARE_PRICE_GUARANTEE_FUND));
+        assert_eq!(transfer_deposit(&mut emulator, "alice", ntoy(10)), Some(0));
+        let receipts = emulator.stake("alice", ntoy(10));
+        assert_eq!(receipts, ntoy(10));
+
+        // NEAR the pool earns buys no fee shares and doesn't raise the token share price.
+        emulator.skip_epoch(ntoy(5));
+        assert_eq!(emulator.contract.last_total_balance, ntoy(35));
+        assert_eq!(emulator.contract.get_account_staked_balance(OWNER.to_string()).0, 0);
+        assert_eq!(emulator.receipt_value(receipts), ntoy(10));
+        assert_eq!(emulator.contract.total_staked_balance, ntoy(10) + STAKE_SHARE_PRICE_GUARANTEE_FUND);
+    }
+
+    #[test]
+    #[should_panic(expected = "The whitelist only applies to token deposits")]
+    fn test_whitelist_of_native_pool() {
+        let mut emulator = Emulator::new(ntoy(30), reward_fee(10, 100));
+        emulator.update_context(OWNER, 0);
+        emulator.contract.set_deposit_whitelist(DepositWhitelist::Local);
+    }
+
+    /// What a delegator put into and took out of the pool.
+    #[derive(Default)]
+    struct Delegator {
+        deposited: Balance,
+        withdrawn: Balance,
+        receipts: NumStakeShares,
+    }
+
+    /// Runs random actions through the pool, each one is `(action, delegator, amount)`, and checks
+    /// the invariants after every step.
+    fn run_actions(actions: Vec<(u8, u8, u64)>) -> bool {
+        let mut emulator = Emulator::new(ntoy(30), reward_fee(10, 100));
+        let mut delegators: HashMap<&str, Delegator> = HashMap::new();
+        let mut total_reward: Balance = 0;
+        let mut price = emulator.share_price_fraction();
+
+        for (action, delegator, amount) in actions {
+            let account_id = ["alice", "bob", "carol"][delegator as usize % 3];
+            let amount = Balance::from(amount);
+            emulator.update_context(account_id, 0);
+            let account = emulator.contract.internal_get_account(&account_id.to_string());
+            let delegator = delegators.entry(account_id).or_default();
+            match action % 5 {
+                0 => {
+                    let deposit = 1 + amount * 100_000_000;
+                    emulator.deposit(account_id, deposit);
+                    delegator.deposited += deposit;
+                }
+                1 => {
+                    let stake = amount % (account.unstaked + 1);
+                    if stake > 0 && emulator.contract.num_shares_from_staked_amount_rounded_down(stake) > 0 {
+                        delegator.receipts += emulator.stake(account_id, stake);
+                    }
+                }
+                2 => {
+                    let redeemed = amount % (delegator.receipts + 1);
+                    if redeemed > 0 {
+                        emulator.redeem(account_id, redeemed);
+                        delegator.receipts -= redeemed;
+                    }
+                }
+                3 => {
+                    let withdrawn = amount % (account.available_balance(emulator.epoch_height) + 1);
+                    if withdrawn > 0 {
+                        emulator.withdraw(account_id, withdrawn);
+                        delegator.withdrawn += withdrawn;
+                    }
+                }
+                _ => {
+                    let reward = amount * 1_000_000;
+                    emulator.skip_epoch(reward);
+                    total_reward += reward;
+                }
+            }
+
+            emulator.assert_invariants(price);
+            price = emulator.share_price_fraction();
+            for (account_id, delegator) in delegators.iter() {
+                let unstaked = emulator.contract.get_account_unstaked_balance(account_id.to_string()).0;
+                let holdings = delegator.withdrawn + unstaked + emulator.receipt_value(delegator.receipts);
+                assert!(holdings <= delegator.deposited + total_reward);
+                assert!(delegator.withdrawn <= delegator.deposited + total_reward);
+            }
+        }
+        true
+    }
+
+    #[quickcheck]
+    fn qc_invariants_hold_for_random_actions(actions: Vec<(u8, u8, u64)>) -> bool {
+        run_actions(actions)
+    }
+
+    #[quickcheck]
+    fn qc_round_trip_returns_the_deposit(deposit: u64, reward: u64) -> bool {
+        let deposit = ntoy(1) + Balance::from(deposit);
+        let reward = Balance::from(reward);
+        let mut emulator = Emulator::new(ntoy(30), reward_fee(10, 100));
+        emulator.deposit("alice", deposit);
+        let receipts = emulator.stake("alice", deposit);
+        emulator.skip_epoch(reward);
+        emulator.redeem("alice", receipts);
+        emulator.epoch_height += NUM_EPOCHS_TO_UNLOCK;
+        emulator.update_context("alice", 0);
+        let unstaked = emulator.contract.get_account_unstaked_balance("alice".to_string()).0;
+        emulator.withdraw("alice", unstaked);
+
+        // Rounding never costs more than a yocto per conversion, and the rewards are shared.
+        unstaked + 2 >= deposit && unstaked <= deposit + reward
+    }
+
+    #[test]
+    fn test_invariants_hold_for_edge_cases() {
+        // Staking and redeeming single yoctos at a share price above 1.
+        assert!(run_actions(vec![(0, 0, 1), (4, 0, u64::MAX), (1, 0, 3), (2, 0, 1), (3, 0, u64::MAX)]));
+        // Withdrawing a deposit made while an unstake ticket is pending.
+        assert!(run_actions(vec![(0, 1, 50), (1, 1, u64::MAX), (2, 1, u64::MAX), (0, 1, 7), (3, 1, u64::MAX)]));
+    }
+}
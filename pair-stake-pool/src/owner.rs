@@ -6,6 +6,20 @@ impl StakingContract {
     /* Owner's methods */
     /*******************/
 
+    /// Owner's method.
+    /// Hands the owner role over to the given account, e.g. a council multisig.
+    pub fn set_owner(&mut self, owner_id: ValidAccountId) {
+        self.assert_owner();
+        self.owner_id = owner_id.into();
+    }
+
+    /// Owner's method.
+    /// Lets the given voting contract lock staked balances it counts as voting power, or none.
+    pub fn set_vote_locker(&mut self, vote_locker: Option<ValidAccountId>) {
+        self.assert_owner();
+        self.vote_locker = vote_locker.map(|vote_locker| vote_locker.into());
+    }
+
     /// Owner's method.
     /// Updates current public key to the new given public key.
     pub fn update_staking_key(&mut self, stake_public_key: Base58PublicKey) {
@@ -16,19 +30,92 @@ impl StakingContract {
         self.internal_restake();
     }
 
+    /// Owner's method.
+    /// Sends the reward fee shares of the next rewards to the given account.
+    pub fn set_fee_beneficiary(&mut self, fee_beneficiary_id: ValidAccountId) {
+        self.assert_owner();
+        // The rewards up to now go to the previous beneficiary.
+        let need_to_restake = self.internal_ping();
+        self.fee_beneficiary_id = fee_beneficiary_id.into();
+        if need_to_restake {
+            self.internal_restake();
+        }
+    }
+
     /// Owner's method.
     /// Updates current reward fee fraction to the new given fraction.
+    /// A decrease applies right away and cancels any announced increase. An increase, of at most
+    /// `MAX_REWARD_FEE_INCREASE`, is announced to apply in `REWARD_FEE_INCREASE_NOTICE_EPOCHS`
+    /// epochs and replaces any announced one.
     pub fn update_reward_fee_fraction(&mut self, reward_fee_fraction: RewardFeeFraction) {
         self.assert_owner();
         reward_fee_fraction.assert_valid();
 
         let need_to_restake = self.internal_ping();
-        self.reward_fee_fraction = reward_fee_fraction;
+        let no_margin = RewardFeeFraction { numerator: 0, denominator: 1 };
+        if reward_fee_fraction.exceeds(&self.reward_fee_fraction, &no_margin) {
+            assert!(
+                !reward_fee_fraction.exceeds(&self.reward_fee_fraction, &MAX_REWARD_FEE_INCREASE),
+                "The reward fee can't be raised by more than {}/{} at once",
+                MAX_REWARD_FEE_INCREASE.numerator,
+                MAX_REWARD_FEE_INCREASE.denominator
+            );
+            let effective_epoch_height = env::epoch_height() + REWARD_FEE_INCREASE_NOTICE_EPOCHS;
+            env::log(
+                format!(
+                    "Reward fee of {}/{} announced for epoch {}",
+                    reward_fee_fraction.numerator,
+                    reward_fee_fraction.denominator,
+                    effective_epoch_height
+                )
+                .as_bytes(),
+            );
+            self.announced_reward_fee =
+                Some(AnnouncedRewardFee { reward_fee_fraction, effective_epoch_height });
+        } else {
+            self.reward_fee_fraction = reward_fee_fraction;
+            self.announced_reward_fee = None;
+        }
         if need_to_restake {
             self.internal_restake();
         }
     }
 
+    /// Owner's method.
+    /// Sets who can deposit the token of a token pool.
+    pub fn set_deposit_whitelist(&mut self, deposit_whitelist: DepositWhitelist) {
+        self.assert_owner();
+        assert!(
+            self.asset != Asset::Native,
+            "The whitelist only applies to token deposits"
+        );
+        if let DepositWhitelist::External { whitelist_account_id } = &deposit_whitelist {
+            assert!(
+                env::is_valid_account_id(whitelist_account_id.as_bytes()),
+                "Invalid whitelist account ID"
+            );
+        }
+        self.deposit_whitelist = deposit_whitelist;
+    }
+
+    /// Owner's method.
+    /// Adds the given accounts to the `Local` whitelist.
+    pub fn add_whitelisted_accounts(&mut self, account_ids: Vec<ValidAccountId>) {
+        self.assert_owner();
+        for account_id in account_ids {
+            self.whitelisted_accounts.insert(account_id.as_ref());
+        }
+    }
+
+    /// Owner's method.
+    /// Removes the given accounts from the `Local` whitelist. Their balances stay in the pool.
+    pub fn remove_whitelisted_accounts(&mut self, account_ids: Vec<ValidAccountId>) {
+        self.assert_owner();
+        for account_id in account_ids {
+            self.whitelisted_accounts.remove(account_id.as_ref());
+        }
+    }
+
     /// Owner's method.
     /// Calls `vote(is_vote)` on the given voting contract account ID on behalf of the pool.
     pub fn vote(&mut self, voting_account_id: AccountId, is_vote: bool) -> Promise {
@@ -0,0 +1,194 @@
+use near_sdk::test_utils::VMContextBuilder;
+use near_sdk::{testing_env, testing_env_with_promise_results, MockedBlockchain, VMContext};
+
+use crate::*;
+
+pub const OWNER: &str = "owner";
+pub const POOL: &str = "pool";
+pub const TOKEN: &str = "bnear";
+pub const DEPOSIT_TOKEN: &str = "synchro-token";
+
+pub fn ntoy(near_amount: Balance) -> Balance {
+    near_amount * 10u128.pow(24)
+}
+
+pub fn account(account_id: &str) -> ValidAccountId {
+    account_id.try_into().unwrap()
+}
+
+/// Runs the pool in a mocked blockchain and keeps track of its balances between calls.
+pub struct Emulator {
+    pub contract: StakingContract,
+    pub epoch_height: EpochHeight,
+    /// Unlocked balance of the pool.
+    pub amount: Balance,
+    /// Balance of the pool locked by the validator.
+    pub locked_amount: Balance,
+    pub block_timestamp: Timestamp,
+}
+
+impl Emulator {
+    pub fn new(initial_balance: Balance, reward_fee_fraction: RewardFeeFraction) -> Self {
+        Self::with_asset(initial_balance, reward_fee_fraction, Asset::Native)
+    }
+
+    pub fn with_asset(
+        initial_balance: Balance,
+        reward_fee_fraction: RewardFeeFraction,
+        asset: Asset,
+    ) -> Self {
+        testing_env!(VMContextBuilder::new()
+            .current_account_id(account(POOL))
+            .predecessor_account_id(account(OWNER))
+            .account_balance(initial_balance)
+            .build());
+        let contract = StakingContract::new(
+            OWNER.to_string(),
+            "KuTCtARNzxZQ3YvXDeLjx83FDqxv2SdQTSbiq876zR7".try_into().unwrap(),
+            reward_fee_fraction,
+            account(TOKEN),
+            asset,
+        );
+        Emulator {
+            contract,
+            epoch_height: 0,
+            amount: initial_balance,
+            locked_amount: 0,
+            block_timestamp: 0,
+        }
+    }
+
+    pub fn context(&self, predecessor_id: &str, attached_deposit: Balance) -> VMContext {
+        VMContextBuilder::new()
+            .current_account_id(account(POOL))
+            .predecessor_account_id(account(predecessor_id))
+            .epoch_height(self.epoch_height)
+            .block_timestamp(self.block_timestamp)
+            .account_balance(self.amount)
+            .account_locked_balance(self.locked_amount)
+            .attached_deposit(attached_deposit)
+            .build()
+    }
+
+    pub fn update_context(&mut self, predecessor_id: &str, attached_deposit: Balance) {
+        testing_env!(self.context(predecessor_id, attached_deposit));
+    }
+
+    /// Deposits `amount` attached by `account_id` to its unstaked balance.
+    pub fn deposit(&mut self, account_id: &str, amount: Balance) {
+        self.amount += amount;
+        self.update_context(account_id, amount);
+        self.contract.internal_deposit(&account_id.to_string(), amount);
+    }
+
+    /// Stakes `amount` of the unstaked balance of `account_id`, with a successful mint.
+    /// Returns the number of receipts minted.
+    pub fn stake(&mut self, account_id: &str, amount: Balance) -> NumStakeShares {
+        self.stake_with_mint_result(account_id, amount, PromiseResult::Successful(vec![]))
+    }
+
+    /// Stakes `amount` of the unstaked balance of `account_id`, with the given result of the mint.
+    /// Returns the number of receipts to mint.
+    pub fn stake_with_mint_result(
+        &mut self,
+        account_id: &str,
+        amount: Balance,
+        mint_result: PromiseResult,
+    ) -> NumStakeShares {
+        self.update_context(account_id, 0);
+        let num_shares = self.contract.num_shares_from_staked_amount_rounded_down(amount);
+        let charge_amount = self.contract.staked_amount_from_num_shares_rounded_down(num_shares);
+        let stake_amount = self.contract.staked_amount_from_num_shares_rounded_up(num_shares);
+        let account = self.contract.internal_get_account(&account_id.to_string());
+        let ticket_amount = charge_amount.saturating_sub(account.available_balance(self.epoch_height));
+        self.contract.internal_stake(account_id.to_string(), amount);
+
+        testing_env_with_promise_results(self.context(POOL, 0), mint_result);
+        self.contract.on_mint_action(
+            account_id.to_string(),
+            charge_amount.into(),
+            ticket_amount.into(),
+            num_shares.into(),
+            stake_amount.into(),
+        );
+        num_shares
+    }
+
+    /// Redeems `num_shares` receipts sent by `account_id`, with a successful burn.
+    pub fn redeem(&mut self, account_id: &str, num_shares: NumStakeShares) {
+        self.update_context(account_id, 0);
+        self.contract.internal_redeem(account_id.to_string(), account_id.to_string(), num_shares);
+
+        testing_env_with_promise_results(self.context(POOL, 0), PromiseResult::Successful(vec![]));
+        self.contract.on_burn_action(account_id.to_string(), num_shares.into());
+    }
+
+    /// Locks the stake of `account_id` to vote as the vote locker "gov", with the liquid token
+    /// locking `receipts` of it. Returns the staked balance counted as voting power.
+    pub fn lock_for_vote(&mut self, account_id: &str, unlock_time: Timestamp, receipts: NumStakeShares) -> Balance {
+        self.update_context("gov", 0);
+        self.contract.lock_for_vote(account(account_id), unlock_time.into());
+
+        let receipts = near_sdk::serde_json::to_vec(&U128(receipts)).unwrap();
+        testing_env_with_promise_results(self.context(POOL, 0), PromiseResult::Successful(receipts));
+        self.contract.on_receipts_locked(account_id.to_string(), unlock_time.into()).0
+    }
+
+    /// Withdraws `amount` of the available unstaked balance of `account_id`.
+    pub fn withdraw(&mut self, account_id: &str, amount: Balance) {
+        self.update_context(account_id, 0);
+        self.contract.internal_withdraw(amount);
+        self.amount -= amount;
+    }
+
+    /// Moves to the next epoch, in which the validator earned `reward`, and pings the pool.
+    pub fn skip_epoch(&mut self, reward: Balance) {
+        self.epoch_height += 1;
+        self.locked_amount += reward;
+        self.update_context(OWNER, 0);
+        self.contract.internal_ping();
+    }
+
+    /// Value of one "stake" share, scaled by 10^24.
+    pub fn share_price(&self) -> Balance {
+        self.contract.staked_amount_from_num_shares_rounded_down(ntoy(1))
+    }
+
+    /// The "stake" share price as a `(total_staked_balance, total_stake_shares)` fraction.
+    pub fn share_price_fraction(&self) -> (Balance, NumStakeShares) {
+        (self.contract.total_staked_balance, self.contract.total_stake_shares)
+    }
+
+    /// Asserts that the state of the pool is consistent and that the "stake" share price didn't
+    /// decrease since `previous`, taken with `share_price_fraction`.
+    pub fn assert_invariants(&self, previous: (Balance, NumStakeShares)) {
+        let (staked, shares) = self.share_price_fraction();
+        assert!(
+            U256::from(staked) * U256::from(previous.1) >= U256::from(previous.0) * U256::from(shares),
+            "The share price decreased from {}/{} to {}/{}",
+            previous.0,
+            previous.1,
+            staked,
+            shares
+        );
+
+        let accounts: Vec<Account> = self.contract.accounts.values().collect();
+        let account_shares: NumStakeShares = accounts.iter().map(|account| account.stake_shares).sum();
+        assert!(account_shares + self.contract.receipt_shares <= shares);
+        for account in accounts.iter() {
+            assert!(account.locked_balance(0) <= account.unstaked);
+        }
+        // Rounding is paid from the guarantee fund, so the pool always holds what it owes.
+        let unstaked: Balance = accounts.iter().map(|account| account.unstaked).sum();
+        assert!(staked + unstaked <= self.contract.last_total_balance);
+        assert_eq!(self.contract.last_total_balance, self.amount + self.locked_amount);
+    }
+
+    pub fn staked_balance(&self, account_id: &str) -> Balance {
+        self.contract.get_account_staked_balance(account_id.to_string()).0
+    }
+
+    pub fn receipt_value(&self, num_shares: NumStakeShares) -> Balance {
+        self.contract.get_receipt_value(num_shares.into()).0
+    }
+}
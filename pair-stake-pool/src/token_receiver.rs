@@ -4,42 +4,85 @@ use near_sdk::{serde_json, PromiseOrValue};
 use near_sdk::json_types::ValidAccountId;
 
 use crate::*;
-use crate::action::UserAction;
 
 pub const VIRTUAL_ACC: &str = "@";
 
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
-#[serde(untagged)]
 pub enum UserAction {
     DepositAndStake,
 }
 
 #[near_bindgen]
 impl FungibleTokenReceiver for StakingContract {
-    /// Callback on receiving tokens by this contract.
-    /// `msg` format is either "" for deposit or `TokenReceiverMessage`.
-    #[allow(unreachable_code)]
+    /// Callback on receiving the deposit token of a token pool, or receipts to redeem.
+    /// Received receipts are burned and unstaked to the inner account of the sender, they're
+    /// refunded if the burn fails.
+    /// For the deposit token, `msg` format is either "" for deposit or `"DepositAndStake"`. Deposits
+    /// of senders out of the deposit whitelist are refunded.
     fn ft_on_transfer(
         &mut self,
         sender_id: ValidAccountId,
         amount: U128,
         msg: String,
     ) -> PromiseOrValue<U128> {
-        if msg.is_empty() {
+        if env::predecessor_account_id() == self.token_contract {
+            self.internal_ping();
+            return PromiseOrValue::Promise(self.internal_redeem(
+                env::current_account_id(),
+                sender_id.into(),
+                amount.into(),
+            ));
+        }
+
+        match &self.asset {
+            Asset::Token { token_id } => assert_eq!(
+                &env::predecessor_account_id(),
+                token_id,
+                "The pool only takes deposits of its token"
+            ),
+            Asset::Native => env::panic(b"The pool only takes native deposits"),
+        }
+
+        let stake = if msg.is_empty() {
             // Simple deposit.
-            self.internal_deposit(sender_id.as_ref(), amount.into());
-            PromiseOrValue::Value(U128(0))
+            false
         } else {
             let message =
                 serde_json::from_str::<UserAction>(&msg).expect("ERR_JSON_IS_EMPTY");
             match message {
-                UserAction::DepositAndStake => {
-                    self.internal_deposit_and_stake(sender_id.as_ref(), amount.into());
-                    PromiseOrValue::Value(U128(0))
-                },
-                _ => {}
+                UserAction::DepositAndStake => true,
+            }
+        };
+
+        let sender_id: AccountId = sender_id.into();
+        match &self.deposit_whitelist {
+            DepositWhitelist::Open => {}
+            DepositWhitelist::Local => {
+                if !self.whitelisted_accounts.contains(&sender_id) {
+                    return PromiseOrValue::Value(self.internal_reject_deposit(&sender_id, amount));
+                }
+            }
+            DepositWhitelist::External { whitelist_account_id } => {
+                return PromiseOrValue::Promise(
+                    ext_whitelist::is_whitelisted(
+                        sender_id.clone(),
+                        whitelist_account_id,
+                        NO_DEPOSIT,
+                        IS_WHITELISTED_GAS,
+                    )
+                    .then(ext_self::on_whitelist_check(
+                        sender_id,
+                        amount,
+                        stake,
+                        &env::current_account_id(),
+                        NO_DEPOSIT,
+                        ON_WHITELIST_CHECK_GAS,
+                    )),
+                );
             }
         }
+        self.internal_token_deposit(&sender_id, amount.into(), stake);
+        PromiseOrValue::Value(U128(0))
     }
-}
\ No newline at end of file
+}
@@ -8,13 +8,25 @@ pub trait VoteContract {
     fn vote(&mut self, is_vote: bool);
 }
 
+/// Interface for a whitelist of delegators.
+#[ext_contract(ext_whitelist)]
+pub trait WhitelistContract {
+    /// Returns true if the given account may deposit into the pool.
+    fn is_whitelisted(&self, account_id: AccountId) -> bool;
+}
+
 /// Interface for bNear contract.
 #[ext_contract(ext_fungible_token)]
 pub trait FungibleToken {
-    /// Method for staking pool to mint bNEAR to the delegators when they stakes.
-    fn mint(&mut self, account_id: AccountId, amount: Balance);
-    /// Method for staking pool to burn bNEAR from the delegators when they unstakes.
-    fn burn(&mut self, account_id: AccountId, amount: Balance);
+    /// Method for staking pool to mint bNEAR receipts to the delegators when they stake.
+    fn mint_to(&mut self, account_id: AccountId, amount: U128);
+    /// Method for staking pool to burn the bNEAR receipts redeemed by their holders.
+    fn burn_from(&mut self, account_id: AccountId, amount: U128);
+    /// Method for staking pool to lock the bNEAR receipts counted as voting power of their holder.
+    /// Returns the number of receipts locked.
+    fn lock_for_vote(&mut self, account_id: AccountId, unlock_time: U64) -> U128;
+    /// NEP-141 transfer of the deposit token to a withdrawing delegator.
+    fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>);
 }
 
 /// Interface for the contract itself.
@@ -27,26 +39,37 @@ pub trait SelfContract {
     /// action in case of the failure of the first staking action.
     fn on_stake_action(&mut self);
 
+    /// A callback to check the result of the withdrawal of deposit tokens.
+    /// In case the transfer failed, the withdrawn amount is returned to the unstaked balance.
+    fn on_withdraw(&mut self, account_id: AccountId, amount: U128) -> bool;
+
+    /// A callback to check the answer of the external whitelist on a token deposit.
+    /// The deposit is taken if the sender is whitelisted, and refunded otherwise.
+    /// Returns the amount to refund.
+    fn on_whitelist_check(&mut self, sender_id: AccountId, amount: U128, stake: bool) -> U128;
+
     /// A callback to check the result of the minting action.
-    /// In case the account register action couldn't completed due to such as storage staking, the staking
-    /// action changes nothing.
+    /// In case the account register action couldn't completed due to such as storage staking, the
+    /// stake taken by `internal_stake` is given back, `ticket_amount` of it as a new unstake ticket.
+    /// Returns true if the receipts were minted.
     fn on_mint_action(
         &mut self,
         account_id: AccountId,
-        charge_amount: Balance,
-        num_shares: u128,
-    );
+        charge_amount: U128,
+        ticket_amount: U128,
+        num_shares: U128,
+        stake_amount: U128,
+    ) -> bool;
+
+    /// A callback to lock the "stake" shares of the account to vote once the liquid token locked its
+    /// receipts, and to value them together.
+    /// Returns the staked balance they are worth.
+    fn on_receipts_locked(&mut self, account_id: AccountId, unlock_time: U64) -> U128;
 
     /// A callback to check the result of the burning action.
-    /// In case the balance of bNEAR is less than unstaking amount, the unstaking
-    /// action changes nothing.
-    fn on_burn_action(
-        &mut self,
-        account_id: AccountId,
-        num_shares: u128,
-        receive_amount: Balance,
-        principal_reduced: Balance,
-    );
+    /// In case the holder had less bNEAR than redeemed, the unstaking action changes nothing.
+    /// Returns the number of receipts that weren't redeemed.
+    fn on_burn_action(&mut self, account_id: AccountId, num_shares: U128) -> U128;
 }
 
 #[near_bindgen]
@@ -74,98 +97,131 @@ impl StakingContract {
     }
 
     #[private]
-    pub fn on_mint_action(
-        &mut self,
-        account_id: AccountId,
-        charge_amount: Balance,
-        num_shares: u128,
-    ) {
+    pub fn on_withdraw(&mut self, account_id: AccountId, amount: U128) -> bool {
         assert_eq!(
             env::promise_results_count(),
             1,
             "Contract expected a result on the callback"
         );
-
         if let PromiseResult::Successful(_) = env::promise_result(0) {
-            let mut account = self.internal_get_account(&account_id);
+            return true;
+        }
 
-            account.unstaked -= charge_amount;
-            account.stake_shares += num_shares;
-            account.stake_principal += charge_amount;
-            self.internal_save_account(&account_id, &account);
-    
-            // The staked amount that will be added to the total to guarantee the "stake" share price
-            // never decreases. The difference between `stake_amount` and `charge_amount` is paid
-            // from the allocated STAKE_SHARE_PRICE_GUARANTEE_FUND.
-            let stake_amount = self.staked_amount_from_num_shares_rounded_up(num_shares);
-    
-            self.total_staked_balance += stake_amount;
-            self.total_stake_shares += num_shares;
-
-            env::log(
-                format!(
-                    "@{} staking {}. Received {} new staking shares. Total {} unstaked balance, {} staking shares, and {} staking principal.",
-                    account_id, charge_amount, num_shares, account.unstaked, account.stake_shares, account.stake_principal,
-                )
-                .as_bytes(),
-            );
-            env::log(
-                format!(
-                    "Contract total staked balance is {}. Total number of shares {}",
-                    self.total_staked_balance, self.total_stake_shares
-                )
-                .as_bytes(),
-            );
+        let amount: Balance = amount.into();
+        let mut account = self.internal_get_account(&account_id);
+        account.unstaked += amount;
+        self.internal_save_account(&account_id, &account);
+
+        env::log(
+            format!(
+                "@{} withdrawal of {} failed. Restored unstaked balance is {}",
+                account_id, amount, account.unstaked
+            )
+            .as_bytes(),
+        );
+        false
+    }
+
+    #[private]
+    pub fn on_whitelist_check(&mut self, sender_id: AccountId, amount: U128, stake: bool) -> U128 {
+        assert_eq!(
+            env::promise_results_count(),
+            1,
+            "Contract expected a result on the callback"
+        );
+        let is_whitelisted = match env::promise_result(0) {
+            PromiseResult::Successful(value) => {
+                near_sdk::serde_json::from_slice::<bool>(&value).unwrap_or(false)
+            }
+            _ => false,
+        };
+        if !is_whitelisted {
+            return self.internal_reject_deposit(&sender_id, amount);
         }
+        self.internal_token_deposit(&sender_id, amount.into(), stake);
+        U128(0)
     }
 
     #[private]
-    pub fn on_burn_action(
+    pub fn on_mint_action(
         &mut self,
         account_id: AccountId,
-        num_shares: u128,
-        receive_amount: Balance,
-        principal_reduced: Balance,
-    ) {
+        charge_amount: U128,
+        ticket_amount: U128,
+        num_shares: U128,
+        stake_amount: U128,
+    ) -> bool {
         assert_eq!(
             env::promise_results_count(),
             1,
             "Contract expected a result on the callback"
         );
-
         if let PromiseResult::Successful(_) = env::promise_result(0) {
-            let mut account = self.internal_get_account(&account_id);
-
-            account.stake_principal -= principal_reduced;
+            self.internal_restake();
+            return true;
+        }
 
-            account.stake_shares -= num_shares;
-            account.unstaked += receive_amount;
-            account.unstaked_available_epoch_height = env::epoch_height() + NUM_EPOCHS_TO_UNLOCK;
+        let (charge_amount, ticket_amount): (Balance, Balance) = (charge_amount.into(), ticket_amount.into());
+        let mut account = self.internal_get_account(&account_id);
+        account.unstaked += charge_amount - ticket_amount;
+        if ticket_amount > 0 {
+            // What was taken from the unstake tickets is still unstaking.
+            account.add_unstake_ticket(ticket_amount, env::epoch_height() + NUM_EPOCHS_TO_UNLOCK);
+        }
+        self.internal_save_account(&account_id, &account);
+
+        let num_shares: NumStakeShares = num_shares.into();
+        self.receipt_shares -= num_shares;
+        self.total_staked_balance -= Balance::from(stake_amount);
+        self.total_stake_shares -= num_shares;
+        // Another action may have restaked the pending stake in the meantime.
+        self.internal_restake();
+
+        env::log(
+            format!(
+                "@{} minting of {} staking shares failed. Restored unstaked balance is {}",
+                account_id, num_shares, account.unstaked
+            )
+            .as_bytes(),
+        );
+        false
+    }
 
+    #[private]
+    pub fn on_receipts_locked(&mut self, account_id: AccountId, unlock_time: U64) -> U128 {
+        assert_eq!(
+            env::promise_results_count(),
+            1,
+            "Contract expected a result on the callback"
+        );
+        let receipts: U128 = match env::promise_result(0) {
+            PromiseResult::Successful(value) => {
+                near_sdk::serde_json::from_slice(&value).expect("The locked receipts aren't valid")
+            }
+            _ => env::panic(b"Locking the receipts to vote failed"),
+        };
+        let mut account = self.internal_get_account(&account_id);
+        if account.stake_shares > 0 {
+            account.vote_unlock_time = std::cmp::max(account.vote_unlock_time, unlock_time.0);
             self.internal_save_account(&account_id, &account);
+        }
+        self.staked_amount_from_num_shares_rounded_down(account.stake_shares + receipts.0).into()
+    }
 
-            // The amount tokens that will be unstaked from the total to guarantee the "stake" share
-            // price never decreases. The difference between `receive_amount` and `unstake_amount` is
-            // paid from the allocated STAKE_SHARE_PRICE_GUARANTEE_FUND.
-            let unstake_amount = self.staked_amount_from_num_shares_rounded_down(num_shares);
-
-            self.total_staked_balance -= unstake_amount;
-            self.total_stake_shares -= num_shares;
-
-            env::log(
-                format!(
-                    "@{} unstaking {}. Spent {} staking shares. Total {} unstaked balance, {} staking shares, and {} staking principal.",
-                    account_id, receive_amount, num_shares, account.unstaked, account.stake_shares, account.stake_principal,
-                )
-                .as_bytes(),
-            );
-            env::log(
-                format!(
-                    "Contract total staked balance is {}. Total number of shares {}",
-                    self.total_staked_balance, self.total_stake_shares
-                )
-                .as_bytes(),
-            );
+    #[private]
+    pub fn on_burn_action(&mut self, account_id: AccountId, num_shares: U128) -> U128 {
+        assert_eq!(
+            env::promise_results_count(),
+            1,
+            "Contract expected a result on the callback"
+        );
+        if let PromiseResult::Successful(_) = env::promise_result(0) {
+            let num_shares: NumStakeShares = num_shares.into();
+            self.receipt_shares -= num_shares;
+            let account = self.internal_get_account(&account_id);
+            self.internal_unstake(&account_id, account, num_shares);
+            return U128(0);
         }
+        num_shares
     }
-}
\ No newline at end of file
+}
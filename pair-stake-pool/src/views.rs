@@ -13,7 +13,8 @@ impl StakingContract {
 
     /// Returns the staked balance of the given account.
     /// NOTE: This is computed from the amount of "stake" shares the given account has and the
-    /// current amount of total staked balance and total stake shares on the account.
+    /// current amount of total staked balance and total stake shares on the account. The receipts
+    /// of the account aren't included, see `get_receipt_value`.
     pub fn get_account_staked_balance(&self, account_id: AccountId) -> U128 {
         self.get_account(account_id).staked_balance
     }
@@ -24,16 +25,48 @@ impl StakingContract {
         (account.unstaked_balance.0 + account.staked_balance.0).into()
     }
 
-    /// Returns the staking reward of the given account.
+    /// Returns the staking reward of the given account. Its "stake" shares are the fee shares it
+    /// was paid, so their whole staked balance is reward. The reward of receipts is in their value.
     pub fn get_account_stake_reward(&self, account_id: AccountId) -> U128 {
         self.get_account(account_id).stake_reward
     }
 
-    /// Returns `true` if the given account can withdraw tokens in the current epoch.
+    /// Returns the staked balance the given amount of receipts is worth.
+    pub fn get_receipt_value(&self, amount: U128) -> U128 {
+        self.staked_amount_from_num_shares_rounded_down(amount.into()).into()
+    }
+
+    /// Returns the number of "stake" shares held by the receipts in circulation.
+    pub fn get_receipt_shares(&self) -> U128 {
+        self.receipt_shares.into()
+    }
+
+    /// Returns the unstake tickets of the given account, the matured ones that aren't swept yet
+    /// included.
+    pub fn get_account_unstake_tickets(&self, account_id: AccountId) -> Vec<HumanReadableUnstakeTicket> {
+        let epoch_height = env::epoch_height();
+        self.internal_get_account(&account_id)
+            .unstake_tickets
+            .into_iter()
+            .map(|ticket| HumanReadableUnstakeTicket {
+                amount: ticket.amount.into(),
+                unlock_epoch_height: ticket.unlock_epoch_height,
+                can_withdraw: ticket.unlock_epoch_height <= epoch_height,
+            })
+            .collect()
+    }
+
+    /// Returns `true` if the given account can withdraw its whole unstaked balance in the current
+    /// epoch.
     pub fn is_account_unstaked_balance_available(&self, account_id: AccountId) -> bool {
         self.get_account(account_id).can_withdraw
     }
 
+    /// Returns the voting contract allowed to lock staked balances, if any.
+    pub fn get_vote_locker(&self) -> Option<AccountId> {
+        self.vote_locker.clone()
+    }
+
     /// Returns the total staking balance.
     pub fn get_total_staked_balance(&self) -> U128 {
         self.total_staked_balance.into()
@@ -49,11 +82,45 @@ impl StakingContract {
         self.reward_fee_fraction.clone()
     }
 
+    /// Returns the reward fee increase announced to apply from a later epoch, if any.
+    pub fn get_announced_reward_fee(&self) -> Option<AnnouncedRewardFee> {
+        self.announced_reward_fee.clone()
+    }
+
+    /// Returns account ID of the reward fee beneficiary.
+    pub fn get_fee_beneficiary_id(&self) -> AccountId {
+        self.fee_beneficiary_id.clone()
+    }
+
+    /// Returns the number of reward fee shares minted since the initialization.
+    pub fn get_accumulated_fee_shares(&self) -> U128 {
+        self.accumulated_fee_shares.into()
+    }
+
     /// Returns the staking public key
     pub fn get_staking_key(&self) -> Base58PublicKey {
         self.stake_public_key.clone().try_into().unwrap()
     }
 
+    /// Returns what the delegators deposit and withdraw.
+    pub fn get_asset(&self) -> Asset {
+        self.asset.clone()
+    }
+
+    /// Returns who can deposit the token of a token pool.
+    pub fn get_deposit_whitelist(&self) -> DepositWhitelist {
+        self.deposit_whitelist.clone()
+    }
+
+    /// Returns the accounts of the `Local` whitelist.
+    pub fn get_whitelisted_accounts(&self, from_index: u64, limit: u64) -> Vec<AccountId> {
+        let keys = self.whitelisted_accounts.as_vector();
+
+        (from_index..std::cmp::min(from_index + limit, keys.len()))
+            .map(|index| keys.get(index).unwrap())
+            .collect()
+    }
+
     /// Returns true if the staking is paused
     pub fn is_staking_paused(&self) -> bool {
         self.paused
@@ -63,13 +130,12 @@ impl StakingContract {
     pub fn get_account(&self, account_id: AccountId) -> HumanReadableAccount {
         let account = self.internal_get_account(&account_id);
         let staked_balance: Balance = self.staked_amount_from_num_shares_rounded_down(account.stake_shares);
-        let stake_reward: Balance = self.internal_get_stake_reward(&account_id);
         HumanReadableAccount {
             account_id,
             unstaked_balance: account.unstaked.into(),
             staked_balance: staked_balance.into(),
-            can_withdraw: account.unstaked_available_epoch_height <= env::epoch_height(),
-            stake_reward: stake_reward.into(),
+            can_withdraw: account.locked_balance(env::epoch_height()) == 0,
+            stake_reward: staked_balance.into(),
         }
     }
 
@@ -1,4 +1,6 @@
+use std::cmp::max;
 use crate::*;
+use near_sdk::json_types::U64;
 use near_sdk::{assert_one_yocto, env, log, Promise};
 use crate::utils::{GAS_FOR_FT_TRANSFER, GAS_FOR_RESOLVE_TRANSFER, NO_DEPOSIT};
 
@@ -20,6 +22,7 @@ impl Contract {
         let amount: Balance = amount.into();
 
         assert!(self.ft.total_supply > 0, "ERR_EMPTY_TOTAL_SUPPLY");
+        self.assert_not_vote_locked(&account_id, amount);
         let unlocked = (U256::from(amount) * U256::from(self.locked_token_amount) / U256::from(self.ft.total_supply)).as_u128();
 
         self.ft.internal_withdraw(&account_id, amount);
@@ -68,14 +71,98 @@ impl Contract {
         log!("Mint {} Synchro Token to {}", amount, account_id);
     }
 
+    /// Mint `amount` to `account_id`, registering it if needed.
+    /// Only the minter can call it, to issue liquid staking receipts for staked balance.
+    pub fn mint_to(&mut self, account_id: ValidAccountId, amount: U128) {
+        self.assert_minter();
+        let account_id: AccountId = account_id.into();
+        let amount: Balance = amount.into();
+        assert!(amount > 0, "ERR_ZERO_AMOUNT");
+        if !self.ft.accounts.contains_key(&account_id) {
+            self.ft.internal_register_account(&account_id);
+        }
+
+        self.ft.internal_deposit(&account_id, amount);
+        log!("Mint {} Synchro Token to {}", amount, account_id);
+    }
+
+    /// Burn `amount` from the balance of `account_id`.
+    /// Only the minter can call it, to burn liquid staking receipts when they are redeemed.
+    pub fn burn_from(&mut self, account_id: ValidAccountId, amount: U128) {
+        self.assert_minter();
+        let account_id: AccountId = account_id.into();
+        let amount: Balance = amount.into();
+        assert!(amount > 0, "ERR_ZERO_AMOUNT");
+        self.assert_not_vote_locked(&account_id, amount);
+
+        self.ft.internal_withdraw(&account_id, amount);
+        log!("Burn {} Synchro Token from {}", amount, account_id);
+    }
+
+    /// Lock the whole balance of `account_id` until `unlock_time`, or later if it is already
+    /// locked for longer, and return it.
+    /// Only the vote locker can call it, so the balance it counts as voting power can't be
+    /// counted again for another account.
+    pub fn lock_for_vote(&mut self, account_id: ValidAccountId, unlock_time: U64) -> U128 {
+        self.assert_vote_locker();
+        let account_id: AccountId = account_id.into();
+        let balance = self.ft.accounts.get(&account_id).unwrap_or(0);
+        if balance > 0 {
+            let unlock_time = match self.vote_locks.get(&account_id) {
+                Some(lock) => max(lock.unlock_time, unlock_time.0),
+                None => unlock_time.0,
+            };
+            self.vote_locks.insert(&account_id, &VoteLock { amount: balance, unlock_time });
+            log!("Lock {} Synchro Token of {} to vote until {}", balance, account_id, unlock_time);
+        }
+        U128(balance)
+    }
+
     pub fn burn(&mut self, amount: U128) -> Promise {
         assert_one_yocto();
         self.assert_whitelist();
         let account_id = env::predecessor_account_id();
         let amount = amount.into();
+        self.assert_not_vote_locked(&account_id, amount);
         self.ft.internal_withdraw(&account_id, amount);
         log!("Withdraw {} Synchro from {}", amount, account_id);
         // Transferring NEAR and refunding 1 yoctoNEAR.
         Promise::new(account_id).transfer(amount + 1)
     }
+
+    /// Deposit attached NEAR into the storage fund of the predecessor account.
+    /// The fund pays for registering beneficiaries when staking on their behalf.
+    #[payable]
+    pub fn storage_fund_deposit(&mut self) -> U128 {
+        let account_id = env::predecessor_account_id();
+        let amount = env::attached_deposit();
+        assert!(amount > 0, "Requires positive attached deposit");
+
+        let fund = self.storage_funds.get(&account_id).unwrap_or(0) + amount;
+        self.storage_funds.insert(&account_id, &fund);
+        log!("{} deposit {} to storage fund, total {}", account_id, amount, fund);
+        U128(fund)
+    }
+
+    /// Withdraw unused storage fund back to the predecessor account.
+    /// Withdraws the whole fund if `amount` is omitted.
+    /// Requires attached deposit of exactly 1 yoctoNEAR.
+    #[payable]
+    pub fn storage_fund_withdraw(&mut self, amount: Option<U128>) -> Promise {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let fund = self.storage_funds.get(&account_id).unwrap_or(0);
+        let amount: Balance = amount.map(|a| a.into()).unwrap_or(fund);
+        assert!(amount > 0, "ERR_ZERO_AMOUNT");
+        assert!(fund >= amount, "ERR_NOT_ENOUGH_STORAGE_FUND");
+
+        if fund == amount {
+            self.storage_funds.remove(&account_id);
+        } else {
+            self.storage_funds.insert(&account_id, &(fund - amount));
+        }
+        log!("{} withdraw {} from storage fund", account_id, amount);
+        // Transferring NEAR and refunding 1 yoctoNEAR.
+        Promise::new(account_id).transfer(amount + 1)
+    }
 }
\ No newline at end of file
@@ -1,16 +1,109 @@
 use std::cmp::{max, min};
+use std::collections::HashSet;
 use crate::*;
+use crate::utils::U256;
 
 impl Contract {
     pub fn internal_stake(&mut self, account_id: &AccountId, amount: Balance) {
         // check account has registered
         assert!(self.ft.accounts.contains_key(account_id), "Account not registered.");
 
-        if self.ft.total_supply != 0 {
-            assert!(self.locked_token_amount > 0, "ERR_INTERNAL");
-        }
+        let minted = self.num_shares_from_amount(amount);
+        assert!(minted > 0, "ERR_STAKE_TOO_SMALL");
 
         self.locked_token_amount += amount;
+        self.ft.internal_deposit(account_id, minted);
+        log!("{} stake {} for {} shares", account_id, amount, minted);
+    }
+
+    /// Returns the number of shares `amount` of locked token is worth at the current price,
+    /// rounded down.
+    pub(crate) fn num_shares_from_amount(&self, amount: Balance) -> Balance {
+        if self.ft.total_supply == 0 {
+            return amount;
+        }
+        assert!(self.locked_token_amount > 0, "ERR_INTERNAL");
+        (U256::from(amount) * U256::from(self.ft.total_supply) / U256::from(self.locked_token_amount)).as_u128()
+    }
+
+    /// Stake `amount` on behalf of `beneficiaries`, registering the missing ones with the storage
+    /// fund of `sender_id`. Returns the amount to refund, which is the whole `amount` when the
+    /// request can't be fulfilled.
+    pub(crate) fn internal_stake_for(
+        &mut self,
+        sender_id: &AccountId,
+        amount: Balance,
+        beneficiaries: Vec<(ValidAccountId, U128)>,
+    ) -> Balance {
+        if beneficiaries.is_empty() {
+            log!("ERR_NO_BENEFICIARIES");
+            return amount;
+        }
+        let mut total: Balance = 0;
+        for (_, stake_amount) in beneficiaries.iter() {
+            if stake_amount.0 == 0 {
+                log!("ERR_ZERO_STAKE_AMOUNT");
+                return amount;
+            }
+            total = match total.checked_add(stake_amount.0) {
+                Some(total) => total,
+                None => {
+                    log!("ERR_STAKE_AMOUNT_OVERFLOW");
+                    return amount;
+                }
+            };
+        }
+        if total != amount {
+            log!("ERR_STAKE_AMOUNT_MISMATCH: listed {}, transferred {}", total, amount);
+            return amount;
+        }
+        if beneficiaries
+            .iter()
+            .any(|(_, stake_amount)| self.num_shares_from_amount(stake_amount.0) == 0)
+        {
+            log!("ERR_STAKE_TOO_SMALL");
+            return amount;
+        }
+
+        let unregistered: HashSet<&AccountId> = beneficiaries
+            .iter()
+            .map(|(account_id, _)| account_id.as_ref())
+            .filter(|account_id| !self.ft.accounts.contains_key(account_id))
+            .collect();
+        let storage_cost = unregistered.len() as Balance
+            * self.ft.account_storage_usage as Balance
+            * env::storage_byte_cost();
+        if storage_cost > 0 {
+            let fund = self.storage_funds.get(sender_id).unwrap_or(0);
+            if fund < storage_cost {
+                log!("ERR_NOT_ENOUGH_STORAGE_FUND: required {}, available {}", storage_cost, fund);
+                return amount;
+            }
+            if fund == storage_cost {
+                self.storage_funds.remove(sender_id);
+            } else {
+                self.storage_funds.insert(sender_id, &(fund - storage_cost));
+            }
+            for account_id in unregistered {
+                self.ft.internal_register_account(account_id);
+            }
+        }
+
+        for (account_id, stake_amount) in beneficiaries {
+            self.internal_stake(account_id.as_ref(), stake_amount.0);
+            log!("{} stake {} on behalf of {}", sender_id, stake_amount.0, account_id.as_ref());
+        }
+        0
+    }
+
+    /// Panics if taking `amount` from `account_id` would leave less than its balance locked to vote.
+    pub(crate) fn assert_not_vote_locked(&self, account_id: &AccountId, amount: Balance) {
+        if let Some(lock) = self.vote_locks.get(account_id) {
+            if env::block_timestamp() < lock.unlock_time {
+                let balance = self.ft.accounts.get(account_id).unwrap_or(0);
+                assert!(balance.saturating_sub(amount) >= lock.amount, "ERR_LOCKED_FOR_VOTE");
+            }
+        }
     }
 
     pub fn internal_add_reward(&mut self, account_id: &AccountId, amount: Balance) {
@@ -2,10 +2,13 @@ use std::collections::HashMap;
 use near_contract_standards::fungible_token::metadata::{
     FungibleTokenMetadata, FungibleTokenMetadataProvider, FT_METADATA_SPEC,
 };
+use near_contract_standards::fungible_token::core::FungibleTokenCore;
+use near_contract_standards::fungible_token::resolver::FungibleTokenResolver;
 use near_contract_standards::fungible_token::FungibleToken;
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::LookupMap;
 use near_sdk::json_types::{ValidAccountId, U128};
-use near_sdk::{env, log, near_bindgen, AccountId, Balance, PanicOnDefault, PromiseOrValue};
+use near_sdk::{env, log, near_bindgen, AccountId, Balance, PanicOnDefault, PromiseOrValue, Timestamp};
 use crate::utils::{DURATION_30DAYS_IN_SEC, nano_to_sec};
 
 mod action;
@@ -13,6 +16,7 @@ mod owner;
 mod utils;
 mod internal;
 mod token_receiver;
+mod view;
 
 near_sdk::setup_alloc!();
 
@@ -35,6 +39,21 @@ pub struct Contract {
     pub reward_per_sec: Balance,
     /// current account number in contract
     pub account_number: u64,
+    /// NEAR deposited by accounts to pay for registering beneficiaries they stake for
+    pub storage_funds: LookupMap<AccountId, Balance>,
+    /// stake pool allowed to mint and burn the token as a liquid staking receipt
+    pub minter: Option<AccountId>,
+    /// voting contract allowed to lock balances that are counted as voting power
+    pub vote_locker: Option<AccountId>,
+    /// balances locked by the vote locker
+    pub vote_locks: LookupMap<AccountId, VoteLock>,
+}
+
+/// Balance of an account that can't leave it before `unlock_time`, as it was counted to vote.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct VoteLock {
+    pub amount: Balance,
+    pub unlock_time: Timestamp,
 }
 
 #[near_bindgen]
@@ -54,15 +73,64 @@ impl Contract {
             reward_genesis_time_in_sec: initial_reward_genisis_time,
             reward_per_sec: 0,
             account_number: 0,
+            storage_funds: LookupMap::new(b"s".to_vec()),
+            minter: None,
+            vote_locker: None,
+            vote_locks: LookupMap::new(b"l".to_vec()),
         };
 
+        contract.ft.internal_register_account(&env::current_account_id());
         contract.ft.internal_deposit(&env::current_account_id(), 100_000_000_000_000);
 
         contract
     }
 }
 
-near_contract_standards::impl_fungible_token_core!(Contract, ft);
+// Same as `impl_fungible_token_core!`, except that transfers can't take balance locked to vote.
+#[near_bindgen]
+impl FungibleTokenCore for Contract {
+    #[payable]
+    fn ft_transfer(&mut self, receiver_id: ValidAccountId, amount: U128, memo: Option<String>) {
+        self.assert_not_vote_locked(&env::predecessor_account_id(), amount.into());
+        self.ft.ft_transfer(receiver_id, amount, memo)
+    }
+
+    #[payable]
+    fn ft_transfer_call(
+        &mut self,
+        receiver_id: ValidAccountId,
+        amount: U128,
+        memo: Option<String>,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        self.assert_not_vote_locked(&env::predecessor_account_id(), amount.into());
+        self.ft.ft_transfer_call(receiver_id, amount, memo, msg)
+    }
+
+    fn ft_total_supply(&self) -> U128 {
+        self.ft.ft_total_supply()
+    }
+
+    fn ft_balance_of(&self, account_id: ValidAccountId) -> U128 {
+        self.ft.ft_balance_of(account_id)
+    }
+}
+
+#[near_bindgen]
+impl FungibleTokenResolver for Contract {
+    #[private]
+    fn ft_resolve_transfer(
+        &mut self,
+        sender_id: ValidAccountId,
+        receiver_id: ValidAccountId,
+        amount: U128,
+    ) -> U128 {
+        let sender_id: AccountId = sender_id.into();
+        let (used_amount, _) = self.ft.internal_ft_resolve_transfer(&sender_id, receiver_id, amount);
+        used_amount.into()
+    }
+}
+
 near_contract_standards::impl_fungible_token_storage!(Contract, ft);
 
 #[near_bindgen]
@@ -23,6 +23,21 @@ impl Contract {
         self.owner = owner_id.as_ref().clone();
     }
 
+    /// Set the stake pool allowed to mint and burn, or none. Only can be called by owner.
+    pub fn set_minter(&mut self, minter: Option<ValidAccountId>) {
+        self.assert_owner();
+        self.minter = minter.map(|minter| minter.into());
+    }
+
+    /// Set the voting contract allowed to lock balances to vote, or none. Only can be called by owner.
+    /// A liquid staking token is locked by its stake pool, which votes with the receipts.
+    /// There is a single vote locker, so the receipts of a pool (bNEAR) need a deployment of their
+    /// own, apart from the Synchro staking one whose vote locker is the voting contract.
+    pub fn set_vote_locker(&mut self, vote_locker: Option<ValidAccountId>) {
+        self.assert_owner();
+        self.vote_locker = vote_locker.map(|vote_locker| vote_locker.into());
+    }
+
     pub fn set_whitelist(&mut self, new_whitelist: Vec<AccountId>) {
         self.assert_whitelist();
 
@@ -1,9 +1,24 @@
 use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+use near_sdk::serde::Deserialize;
+use near_sdk::serde_json;
 use crate::*;
 
+/// Message parameters to receive via token function call.
+#[derive(Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+enum TokenReceiverMessage {
+    /// Stake on behalf of the listed beneficiaries.
+    /// The listed amounts must sum up to the transferred amount.
+    StakeFor { beneficiaries: Vec<(ValidAccountId, U128)> },
+    /// Deposit the transferred amount as reward to be distributed over time.
+    AddReward,
+}
+
 #[near_bindgen]
 impl FungibleTokenReceiver for Contract {
     /// Callback on receiving tokens by this contract.
+    /// `msg` format is either "" for staking, or a `TokenReceiverMessage` for staking on behalf
+    /// of others or depositing reward. Any other message refunds the whole amount.
     fn ft_on_transfer(
         &mut self,
         sender_id: ValidAccountId,
@@ -19,11 +34,146 @@ impl FungibleTokenReceiver for Contract {
             // user stake.
             self.internal_stake(sender_id.as_ref(), amount);
             PromiseOrValue::Value(U128(0))
+        } else if let Ok(message) = serde_json::from_str::<TokenReceiverMessage>(&msg) {
+            match message {
+                TokenReceiverMessage::StakeFor { beneficiaries } => {
+                    let refund = self.internal_stake_for(sender_id.as_ref(), amount, beneficiaries);
+                    PromiseOrValue::Value(U128(refund))
+                }
+                TokenReceiverMessage::AddReward => {
+                    self.internal_add_reward(sender_id.as_ref(), amount);
+                    PromiseOrValue::Value(U128(0))
+                }
+            }
         } else {
-            // deposit reward
-            log!("Add reward {} token with msg {}", amount, msg);
-            self.internal_add_reward(sender_id.as_ref(), amount);
-            PromiseOrValue::Value(U128(0))
+            log!("ERR_NOT_VALID_MSG: {}", msg);
+            PromiseOrValue::Value(U128(amount))
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::{testing_env, MockedBlockchain};
+
+    use super::*;
+
+    const LOCKED_TOKEN: &str = "ref";
+    const STAKER: &str = "alice";
+    const BENEFICIARY: &str = "bob";
+
+    fn account(account_id: &str) -> ValidAccountId {
+        account_id.try_into().unwrap()
+    }
+
+    fn set_context(predecessor_id: &str, attached_deposit: Balance) {
+        testing_env!(VMContextBuilder::new()
+            .current_account_id(account("synchro"))
+            .predecessor_account_id(account(predecessor_id))
+            .attached_deposit(attached_deposit)
+            .build());
+    }
+
+    /// A contract with `STAKER` registered and some tokens already locked.
+    fn setup() -> Contract {
+        set_context("owner", 0);
+        let mut contract = Contract::new(vec![], account(LOCKED_TOKEN));
+        contract.ft.internal_register_account(&STAKER.to_string());
+        contract.locked_token_amount = contract.ft.total_supply;
+        contract
+    }
+
+    fn transfer(contract: &mut Contract, amount: Balance, msg: &str) -> Balance {
+        set_context(LOCKED_TOKEN, 0);
+        match contract.ft_on_transfer(account(STAKER), U128(amount), msg.to_string()) {
+            PromiseOrValue::Value(refund) => refund.0,
+            PromiseOrValue::Promise(_) => panic!("expected a value"),
+        }
+    }
+
+    fn stake_for_msg(beneficiaries: &[(&str, Balance)]) -> String {
+        let beneficiaries: Vec<String> = beneficiaries
+            .iter()
+            .map(|(account_id, amount)| format!("[\"{}\", \"{}\"]", account_id, amount))
+            .collect();
+        format!("{{\"StakeFor\": {{\"beneficiaries\": [{}]}}}}", beneficiaries.join(","))
+    }
+
+    fn balance_of(contract: &Contract, account_id: &str) -> Balance {
+        contract.ft.accounts.get(&account_id.to_string()).unwrap_or(0)
+    }
+
+    #[test]
+    fn test_stake_for_registered_beneficiary() {
+        let mut contract = setup();
+        contract.ft.internal_register_account(&BENEFICIARY.to_string());
+        let locked = contract.locked_token_amount;
+
+        let msg = stake_for_msg(&[(STAKER, 30), (BENEFICIARY, 70)]);
+        assert_eq!(transfer(&mut contract, 100, &msg), 0);
+        assert_eq!(contract.locked_token_amount, locked + 100);
+        assert_eq!(balance_of(&contract, STAKER), 30);
+        assert_eq!(balance_of(&contract, BENEFICIARY), 70);
+    }
+
+    #[test]
+    fn test_stake_for_mints_shares_at_current_price() {
+        let mut contract = setup();
+        contract.ft.internal_register_account(&BENEFICIARY.to_string());
+        // Distributed reward doubled the value of a share.
+        contract.locked_token_amount *= 2;
+
+        let msg = stake_for_msg(&[(STAKER, 40), (BENEFICIARY, 60)]);
+        assert_eq!(transfer(&mut contract, 100, &msg), 0);
+        assert_eq!(balance_of(&contract, STAKER), 20);
+        assert_eq!(balance_of(&contract, BENEFICIARY), 30);
+
+        // A stake worth no share is refunded.
+        let msg = stake_for_msg(&[(STAKER, 99), (BENEFICIARY, 1)]);
+        assert_eq!(transfer(&mut contract, 100, &msg), 100);
+        assert_eq!(balance_of(&contract, BENEFICIARY), 30);
+    }
+
+    #[test]
+    fn test_stake_for_unregistered_beneficiary_needs_storage_fund() {
+        let mut contract = setup();
+        let locked = contract.locked_token_amount;
+        let msg = stake_for_msg(&[(BENEFICIARY, 100)]);
+
+        // Without a storage fund the whole transfer is refunded.
+        assert_eq!(transfer(&mut contract, 100, &msg), 100);
+        assert!(!contract.ft.accounts.contains_key(&BENEFICIARY.to_string()));
+        assert_eq!(contract.locked_token_amount, locked);
+
+        // The storage fund pays for registering the beneficiary.
+        let storage_cost =
+            contract.ft.account_storage_usage as Balance * env::storage_byte_cost();
+        set_context(STAKER, storage_cost);
+        contract.storage_fund_deposit();
+        assert_eq!(transfer(&mut contract, 100, &msg), 0);
+        assert!(contract.ft.accounts.contains_key(&BENEFICIARY.to_string()));
+        assert_eq!(contract.storage_funds.get(&STAKER.to_string()), None);
+        assert_eq!(contract.locked_token_amount, locked + 100);
+        assert_eq!(balance_of(&contract, BENEFICIARY), 100);
+    }
+
+    #[test]
+    fn test_add_reward() {
+        let mut contract = setup();
+        assert_eq!(transfer(&mut contract, 100, "\"AddReward\""), 0);
+        assert_eq!(contract.undistributed_reward, 100);
+    }
+
+    #[test]
+    fn test_malformed_msg_is_refunded() {
+        let mut contract = setup();
+        let locked = contract.locked_token_amount;
+        for msg in &["reward", "{\"StakeFor\": {}}", "\"StakeFor\""] {
+            assert_eq!(transfer(&mut contract, 100, msg), 100);
         }
+        assert_eq!(contract.undistributed_reward, 0);
+        assert_eq!(contract.locked_token_amount, locked);
     }
-}
\ No newline at end of file
+}
@@ -33,6 +33,22 @@ pub trait Synchro {
 }
 
 impl Contract {
+    pub(crate) fn assert_minter(&self) {
+        assert_eq!(
+            Some(env::predecessor_account_id()),
+            self.minter,
+            "ERR_NOT_MINTER"
+        );
+    }
+
+    pub(crate) fn assert_vote_locker(&self) {
+        assert_eq!(
+            Some(env::predecessor_account_id()),
+            self.vote_locker,
+            "ERR_NOT_VOTE_LOCKER"
+        );
+    }
+
     pub(crate) fn assert_whitelist(&self) {
         if !self.whitelist.contains(&env::predecessor_account_id()) {
             env::panic(format!("Not valid account id").as_ref());
@@ -0,0 +1,44 @@
+use crate::*;
+use near_sdk::json_types::U64;
+use crate::utils::U256;
+
+#[near_bindgen]
+impl Contract {
+    /// Returns the amount of locked token staked in this contract, including distributed reward.
+    pub fn get_locked_token_amount(&self) -> U128 {
+        U128(self.locked_token_amount)
+    }
+
+    /// Returns the amount of locked token the shares of the given account are worth,
+    /// as of the last reward distribution.
+    pub fn get_staked_value(&self, account_id: ValidAccountId) -> U128 {
+        let shares = self.ft.accounts.get(account_id.as_ref()).unwrap_or(0);
+        if shares == 0 {
+            return U128(0);
+        }
+        U128((U256::from(shares) * U256::from(self.locked_token_amount) / U256::from(self.ft.total_supply)).as_u128())
+    }
+
+    /// Returns the stake pool allowed to mint and burn, if any.
+    pub fn get_minter(&self) -> Option<AccountId> {
+        self.minter.clone()
+    }
+
+    /// Returns the voting contract allowed to lock balances, if any.
+    pub fn get_vote_locker(&self) -> Option<AccountId> {
+        self.vote_locker.clone()
+    }
+
+    /// Returns the balance of the given account locked to vote and until when, if it is locked.
+    pub fn get_vote_lock(&self, account_id: ValidAccountId) -> Option<(U128, U64)> {
+        self.vote_locks
+            .get(account_id.as_ref())
+            .filter(|lock| env::block_timestamp() < lock.unlock_time)
+            .map(|lock| (U128(lock.amount), U64(lock.unlock_time)))
+    }
+
+    /// Returns the storage fund of the given account, used to register beneficiaries.
+    pub fn get_storage_fund(&self, account_id: ValidAccountId) -> U128 {
+        U128(self.storage_funds.get(account_id.as_ref()).unwrap_or(0))
+    }
+}
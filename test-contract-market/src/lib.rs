@@ -0,0 +1,117 @@
+/*!
+Mock market that lists and delists pairs through market parameter polls of the gov contract
+*/
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::ValidAccountId;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{
+    env, ext_contract, log, near_bindgen, setup_alloc, AccountId, Balance, Gas, PanicOnDefault,
+    Promise,
+};
+
+setup_alloc!();
+
+const GAS_FOR_CREATE_MARKET_POLL: Gas = 20_000_000_000_000;
+
+const NO_DEPOSIT: Balance = 0;
+
+/// Same JSON as the `MarketParameter` of the gov contract.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum MarketParameter {
+    ListPair { pair_id: String },
+    DelistPair { pair_id: String },
+}
+
+#[ext_contract(ext_gov)]
+pub trait Gov {
+    fn create_market_poll(
+        &mut self,
+        title: String,
+        description: String,
+        parameter: MarketParameter,
+    ) -> u32;
+}
+
+#[near_bindgen]
+#[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
+pub struct Market {
+    gov_id: AccountId,
+    listed_pairs: Vec<String>,
+    /// Index and final status of each finalized market parameter poll.
+    poll_outcomes: Vec<(u32, String)>,
+}
+
+#[near_bindgen]
+impl Market {
+    #[init]
+    pub fn new(gov_id: ValidAccountId) -> Self {
+        assert!(!env::state_exists(), "Already initialized");
+        Self { gov_id: gov_id.into(), listed_pairs: vec![], poll_outcomes: vec![] }
+    }
+
+    /// Asks the voters to list `pair_id`.
+    pub fn propose_listing(&mut self, pair_id: String) -> Promise {
+        let title = format!("List {}", pair_id);
+        self.create_market_poll(title, MarketParameter::ListPair { pair_id })
+    }
+
+    /// Asks the voters to delist `pair_id`.
+    pub fn propose_delisting(&mut self, pair_id: String) -> Promise {
+        let title = format!("Delist {}", pair_id);
+        self.create_market_poll(title, MarketParameter::DelistPair { pair_id })
+    }
+
+    /// Applies the outcome of a passed market parameter poll.
+    pub fn on_market_poll_passed(&mut self, index: u32, parameter: MarketParameter) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.gov_id,
+            "Only the gov contract can apply polls"
+        );
+        log!("Applying poll {}: {:?}", index, parameter);
+        match parameter {
+            MarketParameter::ListPair { pair_id } => {
+                assert!(!self.listed_pairs.contains(&pair_id), "Pair already listed");
+                self.listed_pairs.push(pair_id);
+            }
+            MarketParameter::DelistPair { pair_id } => {
+                let len = self.listed_pairs.len();
+                self.listed_pairs.retain(|listed| *listed != pair_id);
+                assert_ne!(self.listed_pairs.len(), len, "Pair not listed");
+            }
+        }
+    }
+
+    /// Records how a market parameter poll ended. Passed polls are applied on execution.
+    pub fn on_market_poll_finalized(&mut self, index: u32, parameter: MarketParameter, status: String) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.gov_id,
+            "Only the gov contract can report polls"
+        );
+        log!("Poll {} on {:?} finalized as {}", index, parameter, status);
+        self.poll_outcomes.push((index, status));
+    }
+
+    pub fn get_listed_pairs(&self) -> Vec<String> {
+        self.listed_pairs.clone()
+    }
+
+    pub fn get_poll_outcomes(&self) -> Vec<(u32, String)> {
+        self.poll_outcomes.clone()
+    }
+}
+
+impl Market {
+    fn create_market_poll(&self, title: String, parameter: MarketParameter) -> Promise {
+        ext_gov::create_market_poll(
+            title,
+            "Opened by the market".to_string(),
+            parameter,
+            &self.gov_id,
+            NO_DEPOSIT,
+            GAS_FOR_CREATE_MARKET_POLL,
+        )
+    }
+}
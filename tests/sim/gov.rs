@@ -0,0 +1,1033 @@
+use near_sdk::json_types::{Base64VecU8, U128};
+use near_sdk::serde_json::{json, Value};
+use near_sdk_sim::{to_yocto, ExecutionResult, UserAccount, DEFAULT_GAS};
+
+use crate::utils::{
+    deploy_native_pool, gov_transfer_call, init_gov, init_gov_with_stake_pool, init_market, register_user, GOV_MIN_CREATE_POLL_AMOUNT, GOV_VOTING_PERIOD, LIQUID_TOKEN_ID,
+    MARKET_ID, STAKE_POOL_ID,
+};
+
+fn create_poll(user: &UserAccount, title: &str) -> ExecutionResult {
+    let action = json!({ "CreatePoll": { "title": title, "description": "simulated poll" } });
+    gov_transfer_call(user, to_yocto("10"), action)
+}
+
+fn vote(user: &UserAccount, index: u32, vote: bool, amount: u128) -> ExecutionResult {
+    gov_transfer_call(user, amount, json!({ "Vote": { "index": index, "vote": vote } }))
+}
+
+// Returns the reason the gov contract logged for rejecting the message of a transfer, if any.
+fn rejection(outcome: &ExecutionResult) -> Option<String> {
+    outcome
+        .get_receipt_results()
+        .into_iter()
+        .flatten()
+        .flat_map(|result| result.logs().clone())
+        .find_map(|log| log.strip_prefix("Rejected message: ").map(|rejected| rejected.to_string()))
+}
+
+// Returns the event the gov contract logged for closing a poll, if any.
+fn poll_closed(outcome: &ExecutionResult) -> Option<Value> {
+    outcome
+        .logs()
+        .iter()
+        .find_map(|log| log.strip_prefix("Poll closed: "))
+        .map(|event| near_sdk::serde_json::from_str(event).unwrap())
+}
+
+// Total gas burnt by the transaction and the receipts it spawned.
+fn total_gas_burnt(outcome: &ExecutionResult) -> u64 {
+    outcome.gas_burnt()
+        + outcome
+            .get_receipt_results()
+            .iter()
+            .flatten()
+            .map(|result| result.gas_burnt())
+            .sum::<u64>()
+}
+
+fn finalize_poll(user: &UserAccount, index: u32) -> ExecutionResult {
+    user.call(
+        "gov".into(),
+        "finalize_poll",
+        &json!({ "index": index }).to_string().into_bytes(),
+        DEFAULT_GAS,
+        0, // deposit
+    )
+}
+
+fn poll_status(user: &UserAccount, index: u32) -> String {
+    user.view("gov".into(), "get_result", &json!({ "index": index }).to_string().into_bytes())
+        .unwrap_json()
+}
+
+fn total_voted(user: &UserAccount, index: u32) -> u128 {
+    let voted: U128 = user
+        .view(
+            "gov".into(),
+            "get_total_voted_stake",
+            &json!({ "index": index }).to_string().into_bytes(),
+        )
+        .unwrap_json();
+    voted.0
+}
+
+fn tally(user: &UserAccount, index: u32) -> (u128, u128) {
+    let (yes, no): (U128, U128) = user
+        .view("gov".into(), "get_tally", &json!({ "index": index }).to_string().into_bytes())
+        .unwrap_json();
+    (yes.0, no.0)
+}
+
+fn ft_balance(user: &UserAccount) -> u128 {
+    let balance: U128 = user
+        .view(
+            "synchro-token".into(),
+            "ft_balance_of",
+            &json!({ "account_id": user.valid_account_id() }).to_string().into_bytes(),
+        )
+        .unwrap_json();
+    balance.0
+}
+
+// Moves the block time past the voting period of polls created so far.
+fn end_voting_period(user: &UserAccount) {
+    let blocks = GOV_VOTING_PERIOD / 1_000_000_000 + 1;
+    user.borrow_runtime_mut().produce_blocks(blocks).unwrap();
+}
+
+fn fund(root: &UserAccount, user: &UserAccount, amount: u128) {
+    root.call(
+        "synchro-token".into(),
+        "ft_transfer",
+        &json!({
+            "receiver_id": user.valid_account_id(),
+            "amount": U128::from(amount),
+        })
+        .to_string()
+        .into_bytes(),
+        DEFAULT_GAS,
+        1, // deposit
+    )
+    .assert_success();
+}
+
+/// Gas a vote may burn across all of its receipts.
+const MAX_VOTE_GAS: u64 = 100_000_000_000_000;
+
+#[test]
+fn simulate_vote_gas_is_constant_as_polls_grow() {
+    let (root, _ft, _gov, alice) = init_gov(to_yocto("100000"));
+    fund(&root, &alice, to_yocto("1000"));
+
+    create_poll(&root, "first").assert_success();
+    let outcome = vote(&alice, 0, true, to_yocto("1"));
+    outcome.assert_success();
+    let first_vote_gas = total_gas_burnt(&outcome);
+
+    let num_polls = 50;
+    for i in 1..num_polls {
+        create_poll(&root, &format!("poll {}", i)).assert_success();
+        if i + 1 < num_polls {
+            vote(&root, i, i % 2 == 0, to_yocto("1")).assert_success();
+        }
+    }
+
+    let outcome = vote(&alice, num_polls - 1, true, to_yocto("1"));
+    outcome.assert_success();
+    let last_vote_gas = total_gas_burnt(&outcome);
+
+    assert!(
+        last_vote_gas <= MAX_VOTE_GAS,
+        "Vote with {} polls burnt {} gas, more than {}",
+        num_polls,
+        last_vote_gas,
+        MAX_VOTE_GAS
+    );
+    assert!(
+        last_vote_gas * 10 <= first_vote_gas * 11,
+        "Vote gas should not grow with the number of polls"
+    );
+}
+
+#[test]
+fn simulate_paginated_votes() {
+    let (root, _ft, gov, alice) = init_gov(to_yocto("100000"));
+    fund(&root, &alice, to_yocto("1000"));
+
+    create_poll(&root, "pagination").assert_success();
+    vote(&alice, 0, false, to_yocto("2")).assert_success();
+
+    let num_votes: u64 =
+        root.view(gov.account_id(), "get_number_of_votes", br#"{"index": 0}"#).unwrap_json();
+    assert_eq!(num_votes, 1);
+
+    let get_votes = |from_index: u64| -> Value {
+        root.view(
+            gov.account_id(),
+            "get_votes",
+            &json!({ "index": 0, "from_index": from_index, "limit": 10 })
+                .to_string()
+                .into_bytes(),
+        )
+        .unwrap_json()
+    };
+    assert_eq!(
+        get_votes(0),
+        json!([{
+            "account_id": alice.account_id(),
+            "ballot": { "Option": 1 },
+            "amount": to_yocto("2").to_string(),
+            "weight": to_yocto("2").to_string(),
+            "conviction": 0,
+        }])
+    );
+    assert_eq!(get_votes(1), json!([]));
+}
+
+#[test]
+fn simulate_finalize_poll_after_voting_period() {
+    let (root, _ft, _gov, alice) = init_gov(to_yocto("100000"));
+    fund(&root, &alice, to_yocto("20000"));
+
+    create_poll(&root, "reaches quorum").assert_success();
+    create_poll(&root, "misses quorum").assert_success();
+    vote(&alice, 0, true, to_yocto("8000")).assert_success();
+    vote(&root, 0, false, to_yocto("3000")).assert_success();
+    vote(&alice, 1, true, to_yocto("100")).assert_success();
+
+    let outcome = finalize_poll(&alice, 0);
+    assert!(!outcome.is_ok(), "Should panic");
+    assert!(format!("{:?}", outcome.status()).contains("ERR_VOTING_PERIOD_NOT_ENDED"));
+    assert_eq!(poll_status(&root, 0), "InProgress");
+
+    end_voting_period(&root);
+
+    // Votes after the voting period are refunded and not counted.
+    vote(&alice, 0, false, to_yocto("5000"));
+    assert_eq!(total_voted(&root, 0), to_yocto("11000"));
+
+    finalize_poll(&alice, 0).assert_success();
+    finalize_poll(&alice, 1).assert_success();
+    assert_eq!(poll_status(&root, 0), "Passed");
+    assert_eq!(poll_status(&root, 1), "Expired");
+
+    let outcome = finalize_poll(&alice, 0);
+    assert!(!outcome.is_ok(), "Should panic");
+    assert!(format!("{:?}", outcome.status()).contains("ERR_POLL_NOT_IN_PROGRESS"));
+}
+
+#[test]
+fn simulate_repeated_votes_change_and_withdraw() {
+    let (root, _ft, gov, alice) = init_gov(to_yocto("100000"));
+    fund(&root, &alice, to_yocto("1000"));
+    create_poll(&root, "changing minds").assert_success();
+
+    // Voting again on the same side adds up without double counting.
+    vote(&alice, 0, true, to_yocto("5")).assert_success();
+    vote(&alice, 0, true, to_yocto("3")).assert_success();
+    assert_eq!(tally(&root, 0), (to_yocto("8"), 0));
+    assert_eq!(total_voted(&root, 0), to_yocto("8"));
+
+    // Voting on the other side moves the whole vote.
+    vote(&alice, 0, false, to_yocto("2")).assert_success();
+    assert_eq!(tally(&root, 0), (0, to_yocto("10")));
+    assert_eq!(total_voted(&root, 0), to_yocto("10"));
+
+    alice
+        .call(
+            gov.account_id(),
+            "change_vote",
+            &json!({ "index": 0, "ballot": { "Option": 0 } }).to_string().into_bytes(),
+            DEFAULT_GAS,
+            0, // deposit
+        )
+        .assert_success();
+    assert_eq!(tally(&root, 0), (to_yocto("10"), 0));
+    assert_eq!(total_voted(&root, 0), to_yocto("10"));
+    assert_eq!(ft_balance(&alice), to_yocto("990"));
+
+    alice
+        .call(
+            gov.account_id(),
+            "withdraw_vote",
+            &json!({ "index": 0 }).to_string().into_bytes(),
+            DEFAULT_GAS,
+            1, // deposit
+        )
+        .assert_success();
+    assert_eq!(tally(&root, 0), (0, 0));
+    assert_eq!(total_voted(&root, 0), 0);
+    let num_votes: u64 =
+        root.view(gov.account_id(), "get_number_of_votes", br#"{"index": 0}"#).unwrap_json();
+    assert_eq!(num_votes, 0);
+    assert_eq!(ft_balance(&alice), to_yocto("1000"));
+
+    let outcome = alice.call(
+        gov.account_id(),
+        "withdraw_vote",
+        &json!({ "index": 0 }).to_string().into_bytes(),
+        DEFAULT_GAS,
+        1, // deposit
+    );
+    assert!(!outcome.is_ok(), "Should panic");
+    assert!(format!("{:?}", outcome.status()).contains("ERR_NO_VOTE"));
+}
+
+#[test]
+fn simulate_withdraw_vote_deposit_after_finalization() {
+    let (root, _ft, gov, alice) = init_gov(to_yocto("100000"));
+    fund(&root, &alice, to_yocto("20000"));
+    create_poll(&root, "escrow").assert_success();
+    vote(&alice, 0, true, to_yocto("15000")).assert_success();
+
+    let locked: Value = root
+        .view(
+            gov.account_id(),
+            "get_locked_balances",
+            &json!({ "account_id": alice.account_id() }).to_string().into_bytes(),
+        )
+        .unwrap_json();
+    assert_eq!(
+        locked,
+        json!([{ "index": 0, "amount": to_yocto("15000").to_string(), "can_withdraw": false }])
+    );
+
+    let withdraw_deposit = || {
+        alice.call(
+            gov.account_id(),
+            "withdraw_vote_deposit",
+            &json!({ "index": 0 }).to_string().into_bytes(),
+            DEFAULT_GAS,
+            1, // deposit
+        )
+    };
+    let outcome = withdraw_deposit();
+    assert!(!outcome.is_ok(), "Should panic");
+    assert!(format!("{:?}", outcome.status()).contains("ERR_POLL_IN_PROGRESS"));
+
+    end_voting_period(&root);
+    finalize_poll(&alice, 0).assert_success();
+    assert_eq!(poll_status(&root, 0), "Passed");
+
+    withdraw_deposit().assert_success();
+    assert_eq!(ft_balance(&alice), to_yocto("20000"));
+    // The tally is kept after the deposit is returned.
+    assert_eq!(tally(&root, 0), (to_yocto("15000"), 0));
+    let total_locked: U128 = root
+        .view(
+            gov.account_id(),
+            "get_total_locked_balance",
+            &json!({ "account_id": alice.account_id() }).to_string().into_bytes(),
+        )
+        .unwrap_json();
+    assert_eq!(total_locked.0, 0);
+
+    let outcome = withdraw_deposit();
+    assert!(!outcome.is_ok(), "Should panic");
+    assert!(format!("{:?}", outcome.status()).contains("ERR_NO_LOCKED_DEPOSIT"));
+}
+
+#[test]
+fn simulate_poll_deposit_lifecycle() {
+    let initial_balance = to_yocto("100000");
+    let (root, _ft, gov, alice) = init_gov(initial_balance);
+    fund(&root, &alice, to_yocto("20000"));
+
+    // Only the required deposit is kept, the rest of the transfer is refunded.
+    create_poll(&alice, "reaches quorum").assert_success();
+    create_poll(&alice, "misses quorum").assert_success();
+    assert_eq!(ft_balance(&alice), to_yocto("20000") - 2 * GOV_MIN_CREATE_POLL_AMOUNT);
+
+    let outcome = gov_transfer_call(
+        &alice,
+        GOV_MIN_CREATE_POLL_AMOUNT - 1,
+        json!({ "CreatePoll": { "title": "too cheap", "description": "" } }),
+    );
+    let required = GOV_MIN_CREATE_POLL_AMOUNT.to_string();
+    let attached = (GOV_MIN_CREATE_POLL_AMOUNT - 1).to_string();
+    assert_eq!(
+        rejection(&outcome),
+        Some(json!({ "NotEnoughDeposit": { "required": required, "attached": attached } }).to_string())
+    );
+    assert_eq!(ft_balance(&alice), to_yocto("20000") - 2 * GOV_MIN_CREATE_POLL_AMOUNT);
+
+    vote(&root, 0, true, to_yocto("15000")).assert_success();
+    end_voting_period(&root);
+    finalize_poll(&root, 0).assert_success();
+    finalize_poll(&root, 1).assert_success();
+
+    let poll_deposit = |index: u32| -> Value {
+        root.view(gov.account_id(), "get_poll_deposit", &json!({ "index": index }).to_string().into_bytes())
+            .unwrap_json()
+    };
+    // The deposit of a passed poll stays locked while the poll can be vetoed.
+    assert_eq!(poll_deposit(0), json!([GOV_MIN_CREATE_POLL_AMOUNT.to_string(), "Locked"]));
+    assert_eq!(poll_deposit(1), json!([GOV_MIN_CREATE_POLL_AMOUNT.to_string(), "Forfeited"]));
+    assert_eq!(ft_balance(&alice), to_yocto("20000") - 2 * GOV_MIN_CREATE_POLL_AMOUNT);
+
+    let settle = || {
+        root.call(
+            gov.account_id(),
+            "settle_poll_deposit",
+            &json!({ "index": 0 }).to_string().into_bytes(),
+            DEFAULT_GAS,
+            0, // deposit
+        )
+    };
+    let outcome = settle();
+    assert!(format!("{:?}", outcome.status()).contains("ERR_EXECUTION_TIMELOCK"));
+    end_voting_period(&root);
+    settle().assert_success();
+    assert_eq!(poll_deposit(0), json!([GOV_MIN_CREATE_POLL_AMOUNT.to_string(), "Refunded"]));
+    assert_eq!(ft_balance(&alice), to_yocto("20000") - GOV_MIN_CREATE_POLL_AMOUNT);
+    let burned: U128 = root.view(gov.account_id(), "get_burned_amount", b"").unwrap_json();
+    assert_eq!(burned.0, GOV_MIN_CREATE_POLL_AMOUNT);
+
+    // The owner slashes spam polls, but not passed ones. A slashed deposit isn't counted as burned.
+    create_poll(&alice, "spam").assert_success();
+    let slash = |index: u32| {
+        gov.call(gov.account_id(), "slash_poll", &json!({ "index": index }).to_string().into_bytes(), DEFAULT_GAS, 0)
+    };
+    assert!(format!("{:?}", slash(0).status()).contains("ERR_POLL_NOT_SLASHABLE"));
+    slash(2).assert_success();
+    assert_eq!(poll_status(&root, 2), "Expired");
+    assert_eq!(poll_deposit(2), json!([GOV_MIN_CREATE_POLL_AMOUNT.to_string(), "Forfeited"]));
+    let slashed: U128 = root.view(gov.account_id(), "get_slashed_amount", b"").unwrap_json();
+    assert_eq!(slashed.0, GOV_MIN_CREATE_POLL_AMOUNT);
+    let burned: U128 = root.view(gov.account_id(), "get_burned_amount", b"").unwrap_json();
+    assert_eq!(burned.0, GOV_MIN_CREATE_POLL_AMOUNT);
+}
+
+#[test]
+fn simulate_multi_choice_poll() {
+    let (root, _ft, gov, alice) = init_gov(to_yocto("100000"));
+    fund(&root, &alice, to_yocto("20000"));
+
+    let too_long = json!({ "CreatePoll": {
+        "title": "reward rate",
+        "description": "",
+        "kind": { "MultiChoice": { "options": ["5%", "x".repeat(129)], "winning_rule": "Plurality" } },
+    } });
+    let outcome = gov_transfer_call(&alice, to_yocto("10"), too_long);
+    let rejected: Value = near_sdk::serde_json::from_str(&rejection(&outcome).unwrap()).unwrap();
+    assert_eq!(rejected, json!({ "OptionTooLong": { "option_index": 1, "max_length": 128 } }));
+    assert_eq!(ft_balance(&alice), to_yocto("20000"));
+
+    let action = json!({ "CreatePoll": {
+        "title": "reward rate",
+        "description": "pick the new reward rate",
+        "kind": { "MultiChoice": { "options": ["5%", "10%", "15%"], "winning_rule": "Plurality" } },
+    } });
+    gov_transfer_call(&alice, to_yocto("10"), action).assert_success();
+
+    // The yes/no shorthand only applies to yes/no polls and the transfer is refunded.
+    let balance = ft_balance(&alice);
+    let outcome = vote(&alice, 0, true, to_yocto("1"));
+    assert_eq!(rejection(&outcome), Some(json!({ "NotYesNoPoll": { "index": 0 } }).to_string()));
+    assert_eq!(total_voted(&root, 0), 0);
+    assert_eq!(ft_balance(&alice), balance);
+
+    let cast_ballot = |user: &UserAccount, ballot: Value, amount: u128| {
+        gov_transfer_call(user, amount, json!({ "CastBallot": { "index": 0, "ballot": ballot } }))
+    };
+    cast_ballot(&root, json!({ "Split": [[0, 1], [2, 2]] }), to_yocto("12000")).assert_success();
+    cast_ballot(&alice, json!({ "Option": 1 }), to_yocto("3000")).assert_success();
+    cast_ballot(&alice, json!("Abstain"), to_yocto("1000")).assert_success();
+    let outcome = cast_ballot(&alice, json!({ "Option": 3 }), to_yocto("1"));
+    assert_eq!(
+        rejection(&outcome),
+        Some(json!({ "NotValidBallot": { "error": "ERR_NOT_VALID_OPTION" } }).to_string())
+    );
+
+    end_voting_period(&root);
+    finalize_poll(&root, 0).assert_success();
+    assert_eq!(poll_status(&root, 0), "Passed");
+
+    // Alice's second ballot moved her whole vote to abstain, which counts only towards the quorum.
+    let results: Value = root
+        .view(gov.account_id(), "get_results", &json!({ "index": 0 }).to_string().into_bytes())
+        .unwrap_json();
+    assert_eq!(
+        results["options"],
+        json!([
+            { "option": "5%", "amount": to_yocto("4000").to_string() },
+            { "option": "10%", "amount": "0" },
+            { "option": "15%", "amount": to_yocto("8000").to_string() },
+        ])
+    );
+    assert_eq!(results["abstain_amount"], json!(to_yocto("4000").to_string()));
+    assert_eq!(results["winner"], json!(2));
+    assert_eq!(total_voted(&root, 0), to_yocto("16000"));
+}
+
+#[test]
+fn simulate_voting_modes() {
+    let (root, _ft, gov, alice) = init_gov(to_yocto("100000"));
+    fund(&root, &alice, to_yocto("5000"));
+    let mut small_voters = vec![alice];
+    for name in &["bob", "carol", "dave"] {
+        let user = root.create_user(name.to_string(), to_yocto("100"));
+        register_user(&user);
+        fund(&root, &user, to_yocto("3000"));
+        small_voters.push(user);
+    }
+
+    // The same whale against four small voters, once per voting mode.
+    for (index, mode) in ["Linear", "Quadratic", "Conviction"].iter().enumerate() {
+        let action = json!({ "CreatePoll": { "title": mode, "description": "", "voting_mode": mode } });
+        gov_transfer_call(&small_voters[0], to_yocto("10"), action).assert_success();
+
+        let conviction = if *mode == "Conviction" { 4 } else { 0 };
+        let vote_msg = |vote: bool, conviction: u32| {
+            json!({ "Vote": { "index": index, "vote": vote, "conviction": conviction } })
+        };
+        gov_transfer_call(&root, to_yocto("9000"), vote_msg(false, 0)).assert_success();
+        for user in &small_voters {
+            gov_transfer_call(user, to_yocto("1000"), vote_msg(true, conviction)).assert_success();
+        }
+    }
+
+    // Conviction is only accepted by polls in conviction mode and up to the configured maximum.
+    let outcome = gov_transfer_call(
+        &root,
+        to_yocto("1"),
+        json!({ "Vote": { "index": 0, "vote": false, "conviction": 1 } }),
+    );
+    assert_eq!(
+        rejection(&outcome),
+        Some(json!({ "NotValidConviction": { "error": "ERR_CONVICTION_NOT_SUPPORTED" } }).to_string())
+    );
+    let outcome = gov_transfer_call(
+        &root,
+        to_yocto("1"),
+        json!({ "Vote": { "index": 2, "vote": false, "conviction": 7 } }),
+    );
+    assert_eq!(
+        rejection(&outcome),
+        Some(json!({ "NotValidConviction": { "error": "ERR_CONVICTION_TOO_HIGH" } }).to_string())
+    );
+
+    // Locked votes can't be withdrawn while the poll is active.
+    let withdraw = |user: &UserAccount, method: &str, index: u32| {
+        user.call(
+            gov.account_id(),
+            method,
+            &json!({ "index": index }).to_string().into_bytes(),
+            DEFAULT_GAS,
+            1, // deposit
+        )
+    };
+    let outcome = withdraw(&small_voters[1], "withdraw_vote", 2);
+    assert!(format!("{:?}", outcome.status()).contains("ERR_VOTE_LOCKED"));
+
+    end_voting_period(&root);
+    for index in 0..3 {
+        finalize_poll(&root, index).assert_success();
+        assert_eq!(total_voted(&root, index), to_yocto("13000"));
+    }
+    assert_eq!(poll_status(&root, 0), "Rejected");
+    assert_eq!(poll_status(&root, 1), "Passed");
+    assert_eq!(poll_status(&root, 2), "Passed");
+    // Quadratic weights are the square roots of the deposits.
+    assert_eq!(tally(&root, 1), (4 * 31_622_776_601_683, 94_868_329_805_051));
+    assert_eq!(tally(&root, 2), (to_yocto("20000"), to_yocto("9000")));
+
+    // The whale didn't lock anything, the small voters have to wait four lock periods.
+    withdraw(&root, "withdraw_vote_deposit", 2).assert_success();
+    let outcome = withdraw(&small_voters[1], "withdraw_vote_deposit", 2);
+    assert!(format!("{:?}", outcome.status()).contains("ERR_VOTE_LOCKED"));
+    root.borrow_runtime_mut().produce_blocks(4 * GOV_VOTING_PERIOD / 1_000_000_000).unwrap();
+    let balance = ft_balance(&small_voters[1]);
+    withdraw(&small_voters[1], "withdraw_vote_deposit", 2).assert_success();
+    assert_eq!(ft_balance(&small_voters[1]), balance + to_yocto("1000"));
+}
+
+#[test]
+fn simulate_vote_with_stake_needs_stake_sources() {
+    let (root, _ft, gov, alice) = init_gov(to_yocto("100000"));
+    fund(&root, &alice, to_yocto("1000"));
+    create_poll(&alice, "staked voting").assert_success();
+
+    let vote_with_stake = || {
+        alice.call(
+            gov.account_id(),
+            "vote_with_stake",
+            &json!({ "index": 0, "ballot": { "Option": 0 } }).to_string().into_bytes(),
+            DEFAULT_GAS,
+            0, // deposit
+        )
+    };
+    let outcome = vote_with_stake();
+    assert!(format!("{:?}", outcome.status()).contains("ERR_NO_STAKE_SOURCES"));
+
+    // The contract initialized itself, so it is its own owner.
+    let set_stake_sources = |stake_sources: Value| {
+        gov.call(
+            gov.account_id(),
+            "set_stake_sources",
+            &json!({ "stake_sources": stake_sources }).to_string().into_bytes(),
+            DEFAULT_GAS,
+            0, // deposit
+        )
+    };
+    // A pair stake pool is only accepted once it says which asset it stakes.
+    let outcome = set_stake_sources(json!([{ "PairStakePool": { "contract_id": "synchro-token" } }]));
+    assert!(outcome
+        .promise_errors()
+        .iter()
+        .any(|error| format!("{:?}", error.as_ref().unwrap().status()).contains("ERR_ASSET_QUERY_FAILED")));
+    assert!(format!("{:?}", vote_with_stake().status()).contains("ERR_NO_STAKE_SOURCES"));
+
+    set_stake_sources(json!([{ "SynchroStaking": { "contract_id": "synchro-token" } }])).assert_success();
+
+    // A stake source that can't report the staked balance doesn't count any vote.
+    let outcome = vote_with_stake();
+    assert!(outcome
+        .promise_errors()
+        .iter()
+        .any(|error| format!("{:?}", error.as_ref().unwrap().status()).contains("ERR_VOTING_POWER_QUERY_FAILED")));
+    assert_eq!(total_voted(&root, 0), 0);
+}
+
+/// Sends `amount` of the voting token from `user` to the stake pool and stakes it for receipts.
+fn deposit_and_stake(user: &UserAccount, amount: u128) {
+    user.call(
+        "synchro-token".into(),
+        "ft_transfer_call",
+        &json!({
+            "receiver_id": STAKE_POOL_ID,
+            "amount": U128::from(amount),
+            "msg": json!("DepositAndStake").to_string(),
+        })
+        .to_string()
+        .into_bytes(),
+        DEFAULT_GAS,
+        1, // deposit
+    )
+    .assert_success();
+}
+
+#[test]
+fn simulate_vote_with_locked_stake() {
+    let (root, gov, pool, alice) = init_gov_with_stake_pool(to_yocto("100000"));
+
+    let vote_with_stake = |user: &UserAccount| {
+        user.call(
+            gov.account_id(),
+            "vote_with_stake",
+            &json!({ "index": 0, "ballot": { "Option": 0 } }).to_string().into_bytes(),
+            DEFAULT_GAS,
+            0, // deposit
+        )
+    };
+    let stake_sources = || -> Value { root.view(gov.account_id(), "get_stake_sources", b"{}").unwrap_json() };
+    let status_of = |outcome: ExecutionResult| format!("{:?}", outcome.status());
+
+    // The staked balance of a pool is counted in the voting token, so a native pool is refused.
+    deploy_native_pool(&root, "native-pool");
+    let sources = json!([
+        { "PairStakePool": { "contract_id": STAKE_POOL_ID } },
+        { "PairStakePool": { "contract_id": "native-pool" } },
+    ]);
+    let outcome = gov.call(
+        gov.account_id(),
+        "set_stake_sources",
+        &json!({ "stake_sources": sources }).to_string().into_bytes(),
+        DEFAULT_GAS,
+        0, // deposit
+    );
+    assert!(outcome
+        .promise_errors()
+        .iter()
+        .any(|error| format!("{:?}", error.as_ref().unwrap().status()).contains("ERR_POOL_ASSET_NOT_VOTING_TOKEN")));
+    assert_eq!(stake_sources(), json!([{ "PairStakePool": { "contract_id": STAKE_POOL_ID } }]));
+
+    // Root stakes the voting token with the pool.
+    deposit_and_stake(&root, to_yocto("10"));
+    let args = json!({ "account_id": root.account_id() }).to_string().into_bytes();
+    let receipts: U128 = root.view(LIQUID_TOKEN_ID.into(), "ft_balance_of", &args).unwrap_json();
+    let args = json!({ "amount": receipts }).to_string().into_bytes();
+    let staked: U128 = root.view(pool.account_id(), "get_receipt_value", &args).unwrap_json();
+    assert!(to_yocto("10") - staked.0 <= 2);
+
+    fund(&root, &alice, to_yocto("1000"));
+    create_poll(&alice, "staked voting").assert_success();
+    let outcome = vote_with_stake(&root);
+    outcome.assert_success();
+    assert!(outcome.promise_errors().is_empty());
+    assert_eq!(tally(&root, 0), (staked.0, 0));
+    assert_eq!(total_voted(&root, 0), staked.0);
+
+    // The stake can't be counted twice, and an account without stake can't vote with it.
+    assert!(status_of(vote_with_stake(&root)).contains("ERR_STAKE_ALREADY_COUNTED"));
+    let outcome = vote_with_stake(&alice);
+    assert!(outcome
+        .promise_errors()
+        .iter()
+        .any(|error| format!("{:?}", error.as_ref().unwrap().status()).contains("ERR_NO_STAKE")));
+    assert_eq!(total_voted(&root, 0), staked.0);
+}
+
+#[test]
+fn simulate_vote_with_locked_receipts() {
+    let (root, gov, pool, alice) = init_gov_with_stake_pool(to_yocto("100000"));
+
+    let call = |user: &UserAccount, receiver_id: &str, method: &str, args: Value, deposit: u128| {
+        user.call(receiver_id.into(), method, &args.to_string().into_bytes(), DEFAULT_GAS, deposit)
+    };
+    let receipts_of = |user: &UserAccount| -> u128 {
+        let args = json!({ "account_id": user.account_id() }).to_string().into_bytes();
+        let balance: U128 = root.view(LIQUID_TOKEN_ID.into(), "ft_balance_of", &args).unwrap_json();
+        balance.0
+    };
+    let status_of = |outcome: ExecutionResult| format!("{:?}", outcome.status());
+
+    // Alice stakes with the pool and holds the receipts.
+    fund(&root, &alice, to_yocto("1010"));
+    deposit_and_stake(&alice, to_yocto("10"));
+    let receipts = receipts_of(&alice);
+    assert!(receipts > 0);
+    let args = json!({ "amount": U128::from(receipts) }).to_string().into_bytes();
+    let value: U128 = root.view(pool.account_id(), "get_receipt_value", &args).unwrap_json();
+
+    create_poll(&alice, "receipt voting").assert_success();
+    let outcome = call(&alice, &gov.account_id(), "vote_with_stake", json!({ "index": 0, "ballot": { "Option": 0 } }), 0);
+    outcome.assert_success();
+    assert!(outcome.promise_errors().is_empty());
+    assert_eq!(tally(&root, 0), (value.0, 0));
+
+    // The receipts can't be moved to another account and voted again, nor redeemed.
+    let transfer = json!({ "receiver_id": root.account_id(), "amount": U128::from(receipts) });
+    assert!(status_of(call(&alice, LIQUID_TOKEN_ID, "ft_transfer", transfer, 1)).contains("ERR_LOCKED_FOR_VOTE"));
+    call(&alice, STAKE_POOL_ID, "unstake", json!({ "amount": U128::from(receipts) }), 1);
+    assert_eq!(receipts_of(&alice), receipts);
+
+    end_voting_period(&root);
+    call(&alice, STAKE_POOL_ID, "unstake", json!({ "amount": U128::from(receipts) }), 1).assert_success();
+    assert_eq!(receipts_of(&alice), 0);
+}
+
+#[test]
+fn simulate_poll_listing_views() {
+    let (root, _ft, gov, alice) = init_gov(to_yocto("100000"));
+    fund(&root, &alice, to_yocto("20000"));
+    for title in &["first", "second", "third"] {
+        create_poll(&alice, title).assert_success();
+    }
+    vote(&root, 0, true, to_yocto("15000")).assert_success();
+    vote(&alice, 2, false, to_yocto("5")).assert_success();
+    end_voting_period(&root);
+    finalize_poll(&root, 0).assert_success();
+
+    let view = |method: &str, args: Value| -> Value {
+        root.view(gov.account_id(), method, &args.to_string().into_bytes()).unwrap_json()
+    };
+    let poll = view("get_poll", json!({ "index": 0 }));
+    assert_eq!(poll["title"], json!("first"));
+    assert_eq!(poll["status"], json!("Passed"));
+    assert_eq!(poll["option_amounts"], json!([to_yocto("15000").to_string(), "0"]));
+    assert_eq!(poll["number_of_votes"], json!(1));
+
+    let titles = |polls: Value| -> Vec<String> {
+        polls.as_array().unwrap().iter().map(|poll| poll["title"].as_str().unwrap().to_string()).collect()
+    };
+    let all = json!({ "from_index": 0, "limit": 10, "status_filter": null });
+    assert_eq!(titles(view("get_polls", all)), vec!["first", "second", "third"]);
+    let page = json!({ "from_index": 1, "limit": 1, "status_filter": null });
+    assert_eq!(titles(view("get_polls", page)), vec!["second"]);
+    let in_progress = json!({ "from_index": 0, "limit": 10, "status_filter": "InProgress" });
+    assert_eq!(titles(view("get_polls", in_progress)), vec!["second", "third"]);
+    // The limit counts the matching polls only.
+    let in_progress_page = json!({ "from_index": 0, "limit": 1, "status_filter": "InProgress" });
+    assert_eq!(titles(view("get_polls", in_progress_page)), vec!["second"]);
+
+    let votes = view("get_account_votes", json!({ "account_id": alice.account_id() }));
+    assert_eq!(votes.as_array().unwrap().len(), 1);
+    assert_eq!(votes[0]["index"], json!(2));
+    assert_eq!(votes[0]["status"], json!("InProgress"));
+    assert_eq!(votes[0]["ballot"], json!({ "Option": 1 }));
+    assert_eq!(votes[0]["amount"], json!(to_yocto("5").to_string()));
+}
+
+#[test]
+fn simulate_guardian_veto_and_creator_cancel() {
+    let (root, _ft, gov, alice) = init_gov(to_yocto("100000"));
+    fund(&root, &alice, to_yocto("20000"));
+    create_poll(&alice, "vetoed").assert_success();
+    create_poll(&alice, "cancelled").assert_success();
+    create_poll(&alice, "voted").assert_success();
+
+    let call = |user: &UserAccount, method: &str, args: Value| {
+        user.call(gov.account_id(), method, &args.to_string().into_bytes(), DEFAULT_GAS, 0)
+    };
+    let status_of = |outcome: ExecutionResult| format!("{:?}", outcome.status());
+
+    // Only the creator can cancel, with a reason, and only before anyone voted.
+    let cancel = |user: &UserAccount, index: u32, reason: &str| {
+        call(user, "cancel_poll", json!({ "index": index, "reason": reason }))
+    };
+    assert!(status_of(cancel(&root, 1, "not mine")).contains("ERR_NOT_POLL_CREATOR"));
+    assert!(status_of(cancel(&alice, 1, " ")).contains("ERR_REASON_REQUIRED"));
+    vote(&root, 2, true, to_yocto("1")).assert_success();
+    assert!(status_of(cancel(&alice, 2, "too late")).contains("ERR_POLL_HAS_VOTES"));
+    let balance = ft_balance(&alice);
+    cancel(&alice, 1, "duplicate of poll 0").assert_success();
+    assert_eq!(poll_status(&root, 1), "Cancelled");
+    assert_eq!(ft_balance(&alice), balance + GOV_MIN_CREATE_POLL_AMOUNT);
+
+    vote(&root, 0, true, to_yocto("15000")).assert_success();
+    end_voting_period(&root);
+    finalize_poll(&root, 0).assert_success();
+    assert_eq!(poll_status(&root, 0), "Passed");
+
+    // The owner can't stop a passed poll, only the guardian can veto it.
+    let stop = call(&gov, "stop_vote", json!({ "index": 0, "reason": "too late" }));
+    assert!(status_of(stop).contains("ERR_POLL_NOT_IN_PROGRESS"));
+    assert_eq!(poll_status(&root, 0), "Passed");
+
+    let veto = |user: &UserAccount, reason: &str| call(user, "veto_poll", json!({ "index": 0, "reason": reason }));
+    assert!(status_of(veto(&root, "no guardian")).contains("ERR_NO_GUARDIAN"));
+    call(&gov, "set_guardian", json!({ "guardian": root.account_id() })).assert_success();
+    assert!(status_of(veto(&alice, "not the guardian")).contains("ERR_NOT_ALLOWED_GUARDIAN"));
+    let outcome = veto(&root, "drains the treasury");
+    outcome.assert_success();
+    assert_eq!(
+        poll_closed(&outcome),
+        Some(json!({ "index": 0, "status": "Vetoed", "account_id": "root", "reason": "drains the treasury" }))
+    );
+    assert_eq!(poll_status(&root, 0), "Vetoed");
+
+    // The simulated polls forfeit the deposit on veto, and the actions can't run anymore.
+    let deposit: Value = root
+        .view(gov.account_id(), "get_poll_deposit", &json!({ "index": 0 }).to_string().into_bytes())
+        .unwrap_json();
+    assert_eq!(deposit, json!([GOV_MIN_CREATE_POLL_AMOUNT.to_string(), "Forfeited"]));
+    end_voting_period(&root);
+    assert!(status_of(call(&root, "execute", json!({ "index": 0 }))).contains("ERR_POLL_NOT_PASSED"));
+}
+
+#[test]
+fn simulate_rejected_messages_are_refunded() {
+    let (root, _ft, _gov, alice) = init_gov(to_yocto("100000"));
+    fund(&root, &alice, to_yocto("100"));
+
+    let assert_refunded = |outcome: ExecutionResult, rejected: Value| {
+        outcome.assert_success();
+        assert_eq!(rejection(&outcome), Some(rejected.to_string()));
+        assert_eq!(ft_balance(&alice), to_yocto("100"));
+    };
+
+    // Messages without a version aren't parsed anymore.
+    let outcome = alice.call(
+        "synchro-token".into(),
+        "ft_transfer_call",
+        &json!({
+            "receiver_id": "gov",
+            "amount": U128::from(to_yocto("10")),
+            "msg": json!({ "title": "unversioned", "description": "" }).to_string(),
+        })
+        .to_string()
+        .into_bytes(),
+        DEFAULT_GAS,
+        1, // deposit
+    );
+    outcome.assert_success();
+    assert!(rejection(&outcome).unwrap().starts_with("{\"NotValidMessage\""));
+    assert_eq!(ft_balance(&alice), to_yocto("100"));
+
+    let create = |title: String| json!({ "CreatePoll": { "title": title, "description": "" } });
+    assert_refunded(gov_transfer_call(&alice, to_yocto("10"), create(" ".to_string())), json!("EmptyTitle"));
+    assert_refunded(
+        gov_transfer_call(&alice, to_yocto("10"), create("x".repeat(129))),
+        json!({ "TitleTooLong": { "max_length": 128 } }),
+    );
+    let create_with_actions = |method_name: String, args: Vec<u8>, count: usize| {
+        let action = json!({
+            "receiver_id": "market",
+            "method_name": method_name,
+            "args": Base64VecU8::from(args),
+            "deposit": "0",
+            "gas": "0",
+        });
+        json!({ "CreatePoll": { "title": "actions", "description": "", "actions": vec![action; count] } })
+    };
+    assert_refunded(
+        gov_transfer_call(&alice, to_yocto("10"), create_with_actions("m".to_string(), vec![], 9)),
+        json!({ "TooManyActions": { "max_actions": 8 } }),
+    );
+    assert_refunded(
+        gov_transfer_call(&alice, to_yocto("10"), create_with_actions("m".repeat(257), vec![], 1)),
+        json!({ "MethodNameTooLong": { "action_index": 0, "max_length": 256 } }),
+    );
+    assert_refunded(
+        gov_transfer_call(&alice, to_yocto("10"), create_with_actions("m".to_string(), vec![0; 4097], 1)),
+        json!({ "ActionArgsTooLong": { "action_index": 0, "max_length": 4096 } }),
+    );
+    assert_refunded(
+        gov_transfer_call(&alice, to_yocto("10"), json!({ "Vote": { "index": 0, "vote": true } })),
+        json!({ "NotValidPollIndex": { "index": 0 } }),
+    );
+
+    create_poll(&alice, "valid").assert_success();
+    assert_eq!(poll_status(&root, 0), "InProgress");
+}
+
+#[test]
+fn simulate_market_parameter_polls() {
+    let (root, gov, market, alice) = init_market(to_yocto("100000"));
+
+    let call = |receiver: &UserAccount, method: &str, args: Value| {
+        alice.call(receiver.account_id(), method, &args.to_string().into_bytes(), DEFAULT_GAS, 0)
+    };
+    let status_of = |outcome: ExecutionResult| format!("{:?}", outcome.status());
+    let listed_pairs =
+        || -> Vec<String> { root.view(market.account_id(), "get_listed_pairs", b"").unwrap_json() };
+
+    // Only the market opens market parameter polls.
+    let parameter = json!({ "ListPair": { "pair_id": "a-b" } });
+    let args = json!({ "title": "List", "description": "", "parameter": parameter });
+    assert!(status_of(call(&gov, "create_market_poll", args)).contains("ERR_NOT_VALID_ACCOUNT"));
+
+    call(&market, "propose_listing", json!({ "pair_id": "wnear-synchro" })).assert_success();
+    let poll: Value = root
+        .view(gov.account_id(), "get_poll", &json!({ "index": 0 }).to_string().into_bytes())
+        .unwrap_json();
+    assert_eq!(poll["creator_id"], json!(MARKET_ID));
+    assert_eq!(poll["deposit_amount"], json!("0"));
+    assert_eq!(poll["market_parameter"], json!({ "ListPair": { "pair_id": "wnear-synchro" } }));
+
+    vote(&root, 0, true, to_yocto("15000")).assert_success();
+    end_voting_period(&root);
+    finalize_poll(&root, 0).assert_success();
+    assert_eq!(poll_status(&root, 0), "Passed");
+    assert!(listed_pairs().is_empty());
+    let poll_outcomes =
+        || -> Vec<(u32, String)> { root.view(market.account_id(), "get_poll_outcomes", b"").unwrap_json() };
+    assert_eq!(poll_outcomes(), vec![(0, "Passed".to_string())]);
+
+    // The market gets the outcome once the execution timelock has elapsed.
+    assert!(status_of(call(&gov, "execute", json!({ "index": 0 }))).contains("ERR_EXECUTION_TIMELOCK"));
+    end_voting_period(&root);
+    call(&gov, "execute", json!({ "index": 0 })).assert_success();
+    assert_eq!(listed_pairs(), vec!["wnear-synchro".to_string()]);
+
+    // A rejected poll is reported to the market, but never applied.
+    call(&market, "propose_delisting", json!({ "pair_id": "wnear-synchro" })).assert_success();
+    vote(&root, 1, false, to_yocto("15000")).assert_success();
+    end_voting_period(&root);
+    finalize_poll(&root, 1).assert_success();
+    assert_eq!(poll_status(&root, 1), "Rejected");
+    assert_eq!(poll_outcomes(), vec![(0, "Passed".to_string()), (1, "Rejected".to_string())]);
+    assert!(status_of(call(&gov, "execute", json!({ "index": 1 }))).contains("ERR_POLL_NOT_PASSED"));
+    assert_eq!(listed_pairs(), vec!["wnear-synchro".to_string()]);
+}
+
+#[test]
+fn simulate_execute_retry_and_cancel_execution() {
+    let (root, gov, market, alice) = init_market(to_yocto("100000"));
+    fund(&root, &alice, to_yocto("100"));
+
+    let call = |user: &UserAccount, method: &str, args: Value| {
+        user.call(gov.account_id(), method, &args.to_string().into_bytes(), DEFAULT_GAS, 0)
+    };
+    let status_of = |outcome: ExecutionResult| format!("{:?}", outcome.status());
+    let listed_pairs =
+        || -> Vec<String> { root.view(market.account_id(), "get_listed_pairs", b"").unwrap_json() };
+    let action_statuses = |index: u32| -> Vec<String> {
+        let actions: Value = root
+            .view(gov.account_id(), "get_actions", &json!({ "index": index }).to_string().into_bytes())
+            .unwrap_json();
+        actions.as_array().unwrap().iter().map(|action| action["status"].as_str().unwrap().to_string()).collect()
+    };
+    let action = |receiver_id: &str, method_name: &str, args: Value| {
+        json!({
+            "receiver_id": receiver_id,
+            "method_name": method_name,
+            "args": Base64VecU8::from(args.to_string().into_bytes()),
+            "deposit": "0",
+            "gas": "20000000000000",
+        })
+    };
+    let apply = |index: u32, parameter: Value| {
+        action(MARKET_ID, "on_market_poll_passed", json!({ "index": index, "parameter": parameter }))
+    };
+    let create = |title: &str, actions: Vec<Value>| {
+        gov_transfer_call(
+            &alice,
+            to_yocto("10"),
+            json!({ "CreatePoll": { "title": title, "description": "", "actions": actions } }),
+        )
+    };
+
+    // Actions can't call the voting token, which holds the deposits of the voters.
+    let drain = action(
+        "synchro-token",
+        "ft_transfer",
+        json!({ "receiver_id": alice.account_id(), "amount": to_yocto("1").to_string() }),
+    );
+    let outcome = create("drain", vec![drain]);
+    outcome.assert_success();
+    assert_eq!(rejection(&outcome), Some(json!({ "ActionTargetsVotingToken": { "action_index": 0 } }).to_string()));
+
+    // Nor the voting contract, whose private callbacks would take the call for one of its own and
+    // settle a deposit that was already settled.
+    let settle_again = action(
+        "gov",
+        "on_deposit_transferred",
+        json!({ "index": 0, "settled_status": "Refunded" }),
+    );
+    let outcome = create("settle again", vec![apply(0, json!({ "ListPair": { "pair_id": "e-f" } })), settle_again]);
+    outcome.assert_success();
+    assert_eq!(rejection(&outcome), Some(json!({ "ActionTargetsVotingContract": { "action_index": 1 } }).to_string()));
+    assert_eq!(ft_balance(&alice), to_yocto("100"));
+
+    // Poll 0 delists a pair that only poll 1 lists, poll 2 delists a pair nobody lists.
+    create(
+        "list and delist",
+        vec![
+            apply(0, json!({ "ListPair": { "pair_id": "a-b" } })),
+            apply(0, json!({ "DelistPair": { "pair_id": "c-d" } })),
+        ],
+    )
+    .assert_success();
+    call(&market, "propose_listing", json!({ "pair_id": "c-d" })).assert_success();
+    create("delist unknown", vec![apply(2, json!({ "DelistPair": { "pair_id": "x-y" } }))]).assert_success();
+    for index in 0..3 {
+        vote(&root, index, true, to_yocto("15000")).assert_success();
+    }
+    end_voting_period(&root);
+    for index in 0..3 {
+        finalize_poll(&root, index).assert_success();
+    }
+    end_voting_period(&root);
+
+    // The failed action is recorded and only it is retried.
+    call(&alice, "execute", json!({ "index": 0 })).assert_success();
+    assert_eq!(action_statuses(0), vec!["Succeeded", "Failed"]);
+    assert_eq!(listed_pairs(), vec!["a-b".to_string()]);
+    call(&alice, "execute", json!({ "index": 1 })).assert_success();
+    assert_eq!(listed_pairs(), vec!["a-b".to_string(), "c-d".to_string()]);
+    call(&alice, "execute", json!({ "index": 0 })).assert_success();
+    assert_eq!(action_statuses(0), vec!["Succeeded", "Succeeded"]);
+    assert_eq!(listed_pairs(), vec!["a-b".to_string()]);
+    assert!(status_of(call(&alice, "execute", json!({ "index": 0 }))).contains("ERR_NOTHING_TO_EXECUTE"));
+
+    // The owner gives up on a poll whose action keeps failing.
+    let cancel = |user: &UserAccount, index: u32| {
+        call(user, "cancel_execution", json!({ "index": index, "reason": "pair was never listed" }))
+    };
+    assert!(status_of(cancel(&gov, 2)).contains("ERR_NO_FAILED_ACTION"));
+    call(&alice, "execute", json!({ "index": 2 })).assert_success();
+    assert_eq!(action_statuses(2), vec!["Failed"]);
+    assert!(status_of(cancel(&alice, 2)).contains("ERR_NOT_ALLOWED"));
+    assert!(status_of(cancel(&gov, 0)).contains("ERR_NO_FAILED_ACTION"));
+    let outcome = cancel(&gov, 2);
+    outcome.assert_success();
+    assert_eq!(
+        poll_closed(&outcome),
+        Some(json!({ "index": 2, "status": "ExecutionFailed", "account_id": "gov", "reason": "pair was never listed" }))
+    );
+    assert_eq!(poll_status(&root, 2), "ExecutionFailed");
+    assert!(status_of(call(&alice, "execute", json!({ "index": 2 }))).contains("ERR_POLL_NOT_PASSED"));
+}
@@ -1,3 +1,6 @@
+mod gov;
+mod multisig;
 mod no_macros;
+mod stake_pool;
 mod utils;
 mod with_macros;
@@ -0,0 +1,117 @@
+use near_sdk::json_types::Base64VecU8;
+use near_sdk::serde_json::{json, Value};
+use near_sdk_sim::{to_yocto, ExecutionResult, UserAccount, DEFAULT_GAS};
+
+use crate::utils::{gov_transfer_call, init_multisig_stack, MULTISIG_ID};
+
+const REQUEST_LIFETIME: u64 = 100_000_000_000;
+
+fn call(user: &UserAccount, receiver_id: &str, method: &str, args: Value) -> ExecutionResult {
+    user.call(receiver_id.into(), method, &args.to_string().into_bytes(), DEFAULT_GAS, 0)
+}
+
+fn propose(member: &UserAccount, receiver_id: &str, method: &str, args: Value) -> u64 {
+    let action = json!({
+        "method_name": method,
+        "args": Base64VecU8::from(args.to_string().into_bytes()),
+        "deposit": "0",
+        "gas": "20000000000000",
+    });
+    call(member, MULTISIG_ID, "propose", json!({ "receiver_id": receiver_id, "actions": [action] }))
+        .unwrap_json()
+}
+
+fn request(user: &UserAccount, request_id: u64) -> Value {
+    user.view(MULTISIG_ID.into(), "get_request", &json!({ "request_id": request_id }).to_string().into_bytes())
+        .unwrap_json()
+}
+
+fn status_of(outcome: &ExecutionResult) -> String {
+    format!("{:?}", outcome.status())
+}
+
+#[test]
+fn simulate_stack_owned_by_multisig() {
+    let (root, gov, _multisig, members) = init_multisig_stack(to_yocto("100000"), REQUEST_LIFETIME);
+    let owner: String = root.view("xsynchro".into(), "get_owner", b"").unwrap_json();
+    assert_eq!(owner, MULTISIG_ID);
+    let owner: String = root.view("stake-pool".into(), "get_owner_id", b"").unwrap_json();
+    assert_eq!(owner, MULTISIG_ID);
+
+    let action = json!({ "CreatePoll": { "title": "spam", "description": "" } });
+    gov_transfer_call(&root, to_yocto("10"), action).assert_success();
+    let stop_vote = json!({ "index": 0, "reason": "spam" });
+
+    // A single account can't use the privileged methods anymore.
+    let outcome = call(&gov, "gov", "stop_vote", stop_vote.clone());
+    assert!(status_of(&outcome).contains("ERR_NOT_ALLOWED_OWNER"));
+    let outcome = call(&root, MULTISIG_ID, "propose", json!({ "receiver_id": "gov", "actions": [] }));
+    assert!(status_of(&outcome).contains("ERR_NOT_MEMBER"));
+
+    let request_id = propose(&members[0], "gov", "stop_vote", stop_vote);
+    let outcome = call(&members[0], MULTISIG_ID, "execute", json!({ "request_id": request_id }));
+    assert!(status_of(&outcome).contains("ERR_NOT_ENOUGH_CONFIRMATIONS"));
+    let outcome = call(&members[0], MULTISIG_ID, "confirm", json!({ "request_id": request_id }));
+    assert!(status_of(&outcome).contains("ERR_ALREADY_CONFIRMED"));
+
+    call(&members[1], MULTISIG_ID, "confirm", json!({ "request_id": request_id })).assert_success();
+    call(&members[2], MULTISIG_ID, "execute", json!({ "request_id": request_id })).assert_success();
+    assert_eq!(request(&root, request_id)["status"], json!("Executed"));
+    let status: String =
+        root.view("gov".into(), "get_result", &json!({ "index": 0 }).to_string().into_bytes()).unwrap_json();
+    assert_eq!(status, "Expired");
+
+    // Executed requests can't run twice.
+    let outcome = call(&members[2], MULTISIG_ID, "execute", json!({ "request_id": request_id }));
+    assert!(status_of(&outcome).contains("ERR_REQUEST_NOT_PENDING"));
+}
+
+#[test]
+fn simulate_multisig_revoke_and_expiry() {
+    let (root, _gov, _multisig, members) = init_multisig_stack(to_yocto("100000"), REQUEST_LIFETIME);
+    let set_whitelist = json!({ "new_whitelist": [MULTISIG_ID, "eve"] });
+
+    // The proposer revoking its only confirmation revokes the request.
+    let request_id = propose(&members[0], "xsynchro", "set_whitelist", set_whitelist.clone());
+    call(&members[0], MULTISIG_ID, "revoke", json!({ "request_id": request_id })).assert_success();
+    assert_eq!(request(&root, request_id)["status"], json!("Revoked"));
+    let outcome = call(&members[1], MULTISIG_ID, "confirm", json!({ "request_id": request_id }));
+    assert!(status_of(&outcome).contains("ERR_REQUEST_NOT_PENDING"));
+
+    let request_id = propose(&members[0], "xsynchro", "set_whitelist", set_whitelist);
+    root.borrow_runtime_mut().produce_blocks(REQUEST_LIFETIME / 1_000_000_000 + 1).unwrap();
+    assert_eq!(request(&root, request_id)["is_expired"], json!(true));
+    let outcome = call(&members[1], MULTISIG_ID, "confirm", json!({ "request_id": request_id }));
+    assert!(status_of(&outcome).contains("ERR_REQUEST_EXPIRED"));
+}
+
+#[test]
+fn simulate_multisig_manages_its_members() {
+    let (root, _gov, _multisig, members) = init_multisig_stack(to_yocto("100000"), REQUEST_LIFETIME);
+    let (alice, bob, carol) = (&members[0], &members[1], &members[2]);
+
+    let outcome = call(alice, MULTISIG_ID, "set_threshold", json!({ "threshold": 1 }));
+    assert!(status_of(&outcome).contains("ERR_NOT_ALLOWED"));
+
+    let lower_threshold = propose(alice, MULTISIG_ID, "set_threshold", json!({ "threshold": 1 }));
+    call(carol, MULTISIG_ID, "confirm", json!({ "request_id": lower_threshold })).assert_success();
+
+    let remove_carol = propose(alice, MULTISIG_ID, "remove_member", json!({ "member_id": "carol" }));
+    call(bob, MULTISIG_ID, "confirm", json!({ "request_id": remove_carol })).assert_success();
+    call(alice, MULTISIG_ID, "execute", json!({ "request_id": remove_carol })).assert_success();
+    let remaining: Vec<String> = root.view(MULTISIG_ID.into(), "get_members", b"").unwrap_json();
+    assert_eq!(remaining, vec![alice.account_id(), bob.account_id()]);
+
+    // Confirmations of removed members don't count anymore.
+    assert_eq!(request(&root, lower_threshold)["num_confirmations"], json!(1));
+    let outcome = call(alice, MULTISIG_ID, "execute", json!({ "request_id": lower_threshold }));
+    assert!(status_of(&outcome).contains("ERR_NOT_ENOUGH_CONFIRMATIONS"));
+
+    // The threshold can't exceed the number of members, so the request fails.
+    let raise_threshold = propose(bob, MULTISIG_ID, "set_threshold", json!({ "threshold": 3 }));
+    call(alice, MULTISIG_ID, "confirm", json!({ "request_id": raise_threshold })).assert_success();
+    call(alice, MULTISIG_ID, "execute", json!({ "request_id": raise_threshold })).assert_success();
+    assert_eq!(request(&root, raise_threshold)["status"], json!("Failed"));
+    let threshold: u32 = root.view(MULTISIG_ID.into(), "get_threshold", b"").unwrap_json();
+    assert_eq!(threshold, 2);
+}
@@ -0,0 +1,288 @@
+use near_sdk::json_types::U128;
+use near_sdk::serde_json::{json, Value};
+use near_sdk_sim::{to_yocto, ExecutionResult, UserAccount, DEFAULT_GAS};
+
+use crate::utils::{init_stake_pool, LIQUID_TOKEN_ID};
+
+fn call(user: &UserAccount, pool: &UserAccount, method: &str) -> ExecutionResult {
+    user.call(pool.account_id(), method, b"{}", DEFAULT_GAS, 0)
+}
+
+fn call_with(
+    user: &UserAccount,
+    receiver_id: &str,
+    method: &str,
+    args: Value,
+    deposit: u128,
+) -> ExecutionResult {
+    user.call(receiver_id.into(), method, &args.to_string().into_bytes(), DEFAULT_GAS, deposit)
+}
+
+fn status_of(outcome: ExecutionResult) -> String {
+    format!("{:?}", outcome.status())
+}
+
+fn unstaked_balance(pool: &UserAccount, user: &UserAccount) -> u128 {
+    let args = json!({ "account_id": user.account_id() }).to_string().into_bytes();
+    let balance: U128 =
+        pool.view(pool.account_id(), "get_account_unstaked_balance", &args).unwrap_json();
+    balance.0
+}
+
+fn ft_balance(user: &UserAccount) -> u128 {
+    let args = json!({ "account_id": user.account_id() }).to_string().into_bytes();
+    let balance: U128 = user.view("synchro-token".into(), "ft_balance_of", &args).unwrap_json();
+    balance.0
+}
+
+fn locked_balance(pool: &UserAccount) -> u128 {
+    pool.account().unwrap().locked
+}
+
+fn total_staked_balance(pool: &UserAccount) -> u128 {
+    let total: U128 = pool.view(pool.account_id(), "get_total_staked_balance", b"").unwrap_json();
+    total.0
+}
+
+// Sends `reward` to the pool as if the validator earned it, and moves to a new epoch.
+fn earn_reward(root: &UserAccount, pool: &UserAccount, reward: u128) {
+    root.transfer(pool.account_id(), reward).assert_success();
+    root.borrow_runtime_mut().produce_blocks(10).unwrap();
+}
+
+#[test]
+fn simulate_restake_follows_total_staked_balance() {
+    let (root, pool, _alice) = init_stake_pool(to_yocto("100"), json!("Native"));
+    assert_eq!(locked_balance(&pool), 0);
+
+    // Pinging in a new epoch stakes the whole staked balance with the validator key.
+    root.borrow_runtime_mut().produce_blocks(10).unwrap();
+    call(&root, &pool, "ping").assert_success();
+    assert!(total_staked_balance(&pool) > 0);
+    assert_eq!(locked_balance(&pool), total_staked_balance(&pool));
+
+    earn_reward(&root, &pool, to_yocto("10"));
+    let previous = total_staked_balance(&pool);
+    call(&root, &pool, "ping").assert_success();
+    assert!(total_staked_balance(&pool) >= previous + to_yocto("10"));
+    assert_eq!(locked_balance(&pool), total_staked_balance(&pool));
+
+    // Nothing is restaked while the staking is paused.
+    call(&root, &pool, "pause_staking").assert_success();
+    let locked = locked_balance(&pool);
+    earn_reward(&root, &pool, to_yocto("10"));
+    call(&root, &pool, "ping").assert_success();
+    assert_eq!(locked_balance(&pool), locked);
+    assert!(total_staked_balance(&pool) > locked);
+
+    call(&root, &pool, "resume_staking").assert_success();
+    assert_eq!(locked_balance(&pool), total_staked_balance(&pool));
+}
+
+#[test]
+fn simulate_token_pool_deposit_and_withdraw() {
+    let (root, pool, alice) =
+        init_stake_pool(to_yocto("100"), json!({ "Token": { "token_id": "synchro-token" } }));
+    let amount = to_yocto("10");
+    let transfer = json!({ "receiver_id": alice.account_id(), "amount": U128::from(amount) });
+    call_with(&root, "synchro-token", "ft_transfer", transfer, 1).assert_success();
+
+    let deposit =
+        json!({ "receiver_id": pool.account_id(), "amount": U128::from(amount), "msg": "" });
+    call_with(&alice, "synchro-token", "ft_transfer_call", deposit, 1).assert_success();
+    assert_eq!(unstaked_balance(&pool, &alice), amount);
+    assert_eq!(ft_balance(&alice), 0);
+    let outcome = call_with(&alice, "stake-pool", "deposit", json!({}), to_yocto("1"));
+    assert!(status_of(outcome).contains("The pool only takes deposits of its token"));
+
+    // The tokens aren't staked with the validator, and the NEAR the pool earns isn't theirs.
+    let total = total_staked_balance(&pool);
+    earn_reward(&root, &pool, to_yocto("10"));
+    call(&root, &pool, "ping").assert_success();
+    assert_eq!(locked_balance(&pool), 0);
+    assert_eq!(total_staked_balance(&pool), total);
+
+    // Withdrawals are paid in the deposit token.
+    let withdraw = |amount: u128| {
+        call_with(&alice, "stake-pool", "withdraw", json!({ "amount": U128::from(amount) }), 1)
+    };
+    withdraw(to_yocto("4")).assert_success();
+    assert_eq!(unstaked_balance(&pool, &alice), to_yocto("6"));
+    assert_eq!(ft_balance(&alice), to_yocto("4"));
+
+    // A failed transfer restores the unstaked balance.
+    call_with(
+        &alice,
+        "synchro-token",
+        "ft_transfer",
+        json!({ "receiver_id": "root", "amount": U128::from(to_yocto("4")) }),
+        1,
+    )
+    .assert_success();
+    call_with(&alice, "synchro-token", "storage_unregister", json!({}), 1).assert_success();
+    withdraw(to_yocto("6")).assert_success();
+    assert_eq!(unstaked_balance(&pool, &alice), to_yocto("6"));
+}
+
+#[test]
+fn simulate_native_pool_deposit_and_withdraw() {
+    let (root, pool, alice) = init_stake_pool(to_yocto("100"), json!("Native"));
+
+    call_with(&alice, "stake-pool", "deposit", json!({}), to_yocto("10")).assert_success();
+    assert_eq!(unstaked_balance(&pool, &alice), to_yocto("10"));
+
+    // Token deposits are refunded by the token contract.
+    let deposit =
+        json!({ "receiver_id": pool.account_id(), "amount": U128::from(to_yocto("1")), "msg": "" });
+    let balance = ft_balance(&root);
+    call_with(&root, "synchro-token", "ft_transfer_call", deposit, 1);
+    assert_eq!(ft_balance(&root), balance);
+
+    let near_balance = alice.account().unwrap().amount;
+    call_with(&alice, "stake-pool", "withdraw", json!({ "amount": U128::from(to_yocto("4")) }), 1)
+        .assert_success();
+    assert_eq!(unstaked_balance(&pool, &alice), to_yocto("6"));
+    assert!(alice.account().unwrap().amount > near_balance + to_yocto("3"));
+}
+
+fn liquid_balance(user: &UserAccount) -> u128 {
+    let args = json!({ "account_id": user.account_id() }).to_string().into_bytes();
+    let balance: U128 = user.view(LIQUID_TOKEN_ID.into(), "ft_balance_of", &args).unwrap_json();
+    balance.0
+}
+
+fn pool_account(pool: &UserAccount, user: &UserAccount) -> Value {
+    let args = json!({ "account_id": user.account_id() }).to_string().into_bytes();
+    pool.view(pool.account_id(), "get_account", &args).unwrap_json()
+}
+
+fn balance_of(account: &Value, key: &str) -> u128 {
+    account[key].as_str().unwrap().parse().unwrap()
+}
+
+fn receipt_value(pool: &UserAccount, amount: u128) -> u128 {
+    let args = json!({ "amount": U128::from(amount) }).to_string().into_bytes();
+    let value: U128 = pool.view(pool.account_id(), "get_receipt_value", &args).unwrap_json();
+    value.0
+}
+
+#[test]
+fn simulate_liquid_staking_lifecycle() {
+    let (root, pool, alice) =
+        init_stake_pool(to_yocto("1000"), json!({ "Token": { "token_id": "synchro-token" } }));
+    let amount = to_yocto("10");
+    let transfer = json!({ "receiver_id": alice.account_id(), "amount": U128::from(amount) });
+    call_with(&root, "synchro-token", "ft_transfer", transfer, 1).assert_success();
+
+    // Only the pool mints and burns the liquid token.
+    let mint = json!({ "account_id": alice.account_id(), "amount": U128::from(amount) });
+    assert!(status_of(call_with(&alice, LIQUID_TOKEN_ID, "mint_to", mint, 0))
+        .contains("ERR_NOT_MINTER"));
+
+    // Staking mints one receipt per "stake" share.
+    let deposit_and_stake = json!({
+        "receiver_id": pool.account_id(),
+        "amount": U128::from(amount),
+        "msg": json!("DepositAndStake").to_string(),
+    });
+    call_with(&alice, "synchro-token", "ft_transfer_call", deposit_and_stake, 1).assert_success();
+    let receipts = liquid_balance(&alice);
+    let value = receipt_value(&pool, receipts);
+    assert!(amount - value <= 2);
+    assert_eq!(unstaked_balance(&pool, &alice), amount - value);
+
+    // Unstaking burns them again.
+    let unstake = json!({ "amount": U128::from(receipts) });
+    call_with(&alice, "stake-pool", "unstake", unstake, 1).assert_success();
+    let account = pool_account(&pool, &alice);
+    assert_eq!(liquid_balance(&alice), 0);
+    assert_eq!(balance_of(&account, "staked_balance"), 0);
+    assert!(balance_of(&account, "unstaked_balance") + 2 >= amount);
+    assert_eq!(account["can_withdraw"], json!(false));
+}
+
+#[test]
+fn simulate_redeem_transferred_receipts() {
+    let (root, pool, alice) =
+        init_stake_pool(to_yocto("1000"), json!({ "Token": { "token_id": "synchro-token" } }));
+    let bob = root.create_user("bob".to_string(), to_yocto("100"));
+    let amount = to_yocto("10");
+    let transfer = json!({ "receiver_id": alice.account_id(), "amount": U128::from(amount) });
+    call_with(&root, "synchro-token", "ft_transfer", transfer, 1).assert_success();
+    let deposit_and_stake = json!({
+        "receiver_id": pool.account_id(),
+        "amount": U128::from(amount),
+        "msg": json!("DepositAndStake").to_string(),
+    });
+    call_with(&alice, "synchro-token", "ft_transfer_call", deposit_and_stake, 1).assert_success();
+    let receipts = liquid_balance(&alice);
+    assert_eq!(ft_balance(&pool), amount);
+
+    // NEAR the pool earns doesn't make the receipts worth more tokens than the pool holds.
+    earn_reward(&root, &pool, to_yocto("10"));
+    call(&root, &pool, "ping").assert_success();
+    let value = receipt_value(&pool, receipts);
+    assert!(value <= ft_balance(&pool));
+
+    for token_id in ["synchro-token", LIQUID_TOKEN_ID].iter() {
+        let register = json!({ "account_id": bob.account_id() });
+        call_with(&bob, token_id, "storage_deposit", register, to_yocto("0.01")).assert_success();
+    }
+    let transfer = json!({ "receiver_id": bob.account_id(), "amount": U128::from(receipts) });
+    call_with(&alice, LIQUID_TOKEN_ID, "ft_transfer", transfer, 1).assert_success();
+
+    // Bob redeems them by sending them to the pool.
+    let redeem =
+        json!({ "receiver_id": pool.account_id(), "amount": U128::from(receipts), "msg": "" });
+    call_with(&bob, LIQUID_TOKEN_ID, "ft_transfer_call", redeem, 1).assert_success();
+    assert_eq!(liquid_balance(&bob), 0);
+    assert_eq!(liquid_balance(&pool), 0);
+    assert_eq!(unstaked_balance(&pool, &bob), value);
+    assert_eq!(pool_account(&pool, &bob)["can_withdraw"], json!(false));
+    assert!(unstaked_balance(&pool, &alice) <= 2);
+
+    // Alice has nothing left to unstake.
+    let unstake = json!({ "amount": U128::from(receipts) });
+    assert!(status_of(call_with(&alice, "stake-pool", "unstake", unstake, 1))
+        .contains("Not enough staked balance to unstake"));
+
+    // Once unstaked, Bob withdraws the tokens the receipts were worth and no more than the pool
+    // took.
+    root.borrow_runtime_mut().produce_blocks(50).unwrap();
+    call_with(&bob, "stake-pool", "withdraw_all", json!({}), 1).assert_success();
+    assert_eq!(ft_balance(&bob), value);
+    assert_eq!(ft_balance(&pool), amount - value);
+}
+
+#[test]
+fn simulate_local_whitelist_refunds_deposits() {
+    let (root, pool, alice) =
+        init_stake_pool(to_yocto("100"), json!({ "Token": { "token_id": "synchro-token" } }));
+    let amount = to_yocto("10");
+    let transfer = json!({ "receiver_id": alice.account_id(), "amount": U128::from(amount) });
+    call_with(&root, "synchro-token", "ft_transfer", transfer, 1).assert_success();
+
+    let whitelist = json!({ "deposit_whitelist": "Local" });
+    call_with(&root, "stake-pool", "set_deposit_whitelist", whitelist, 0).assert_success();
+    let deposit_whitelist: Value =
+        pool.view(pool.account_id(), "get_deposit_whitelist", b"").unwrap_json();
+    assert_eq!(deposit_whitelist, json!("Local"));
+
+    // Deposits of accounts out of the whitelist are refunded.
+    let deposit =
+        json!({ "receiver_id": pool.account_id(), "amount": U128::from(amount), "msg": "" });
+    call_with(&alice, "synchro-token", "ft_transfer_call", deposit.clone(), 1).assert_success();
+    assert_eq!(ft_balance(&alice), amount);
+    assert_eq!(unstaked_balance(&pool, &alice), 0);
+
+    let accounts = json!({ "account_ids": [alice.account_id()] });
+    call_with(&root, "stake-pool", "add_whitelisted_accounts", accounts, 0).assert_success();
+    call_with(&alice, "synchro-token", "ft_transfer_call", deposit, 1).assert_success();
+    assert_eq!(ft_balance(&alice), 0);
+    assert_eq!(unstaked_balance(&pool, &alice), amount);
+
+    // Only the owner manages the whitelist.
+    let accounts = json!({ "account_ids": ["bob"] });
+    let outcome = call_with(&alice, "stake-pool", "add_whitelisted_accounts", accounts, 0);
+    assert!(status_of(outcome).contains("Can only be called by the owner"));
+}
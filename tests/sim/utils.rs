@@ -2,19 +2,40 @@ use defi::DeFiContract;
 use fungible_token::ContractContract as FtContract;
 
 use near_sdk::json_types::U128;
-use near_sdk::serde_json::json;
+use near_sdk::serde_json::{json, Value};
 use near_sdk_sim::{
     deploy, init_simulator, to_yocto, ContractAccount, UserAccount, DEFAULT_GAS, STORAGE_AMOUNT,
 };
 
-// Load in contract bytes at runtime
+// Load in contract bytes at runtime. Apart from the prebuilt standard fungible token and defi,
+// they are built from this workspace by the `build.rs` of the root project.
 near_sdk_sim::lazy_static_include::lazy_static_include_bytes! {
-    FT_WASM_BYTES => "res/fungible_token.wasm",
+    FT_WASM_BYTES => "res/standard_fungible_token.wasm",
+    SYNCHRO_WASM_BYTES => "res/fungible_token.wasm",
     DEFI_WASM_BYTES => "res/defi.wasm",
+    GOV_WASM_BYTES => "res/gov.wasm",
+    MULTISIG_WASM_BYTES => "res/multisig.wasm",
+    STAKE_POOL_WASM_BYTES => "res/stake_pool.wasm",
+    MARKET_WASM_BYTES => "res/market.wasm",
 }
 
 const FT_ID: &str = "synchro-token";
 const DEFI_ID: &str = "defi";
+const GOV_ID: &str = "gov";
+const STAKING_ID: &str = "xsynchro";
+pub const STAKE_POOL_ID: &str = "stake-pool";
+pub const MULTISIG_ID: &str = "council";
+pub const MARKET_ID: &str = "market";
+pub const LIQUID_TOKEN_ID: &str = "bnear";
+
+/// Proposal deposit required by simulated polls.
+pub const GOV_MIN_CREATE_POLL_AMOUNT: u128 = 1000;
+
+/// Voting period of simulated polls, in nanoseconds. It is also their conviction lock period.
+pub const GOV_VOTING_PERIOD: u64 = 100_000_000_000;
+
+/// Highest conviction of simulated polls.
+pub const GOV_MAX_CONVICTION: u32 = 6;
 
 // Register the given `user` with FT contract
 pub fn register_user(user: &near_sdk_sim::UserAccount) {
@@ -32,9 +53,8 @@ pub fn register_user(user: &near_sdk_sim::UserAccount) {
     .assert_success();
 }
 
-pub fn init_no_macros(initial_balance: u128) -> (UserAccount, UserAccount, UserAccount) {
-    let root = init_simulator(None);
-
+/// Deploys the standard fungible token at `FT_ID` with `initial_balance` owned by `root`.
+fn deploy_ft(root: &UserAccount, initial_balance: u128) -> UserAccount {
     let ft = root.deploy(&FT_WASM_BYTES, FT_ID.into(), STORAGE_AMOUNT);
 
     ft.call(
@@ -51,6 +71,14 @@ pub fn init_no_macros(initial_balance: u128) -> (UserAccount, UserAccount, UserA
     )
     .assert_success();
 
+    ft
+}
+
+pub fn init_no_macros(initial_balance: u128) -> (UserAccount, UserAccount, UserAccount) {
+    let root = init_simulator(None);
+
+    let ft = deploy_ft(&root, initial_balance);
+
     let alice = root.create_user("alice".to_string(), to_yocto("100"));
     register_user(&alice);
 
@@ -61,22 +89,12 @@ pub fn init_with_macros(
     initial_balance: u128,
 ) -> (UserAccount, ContractAccount<FtContract>, ContractAccount<DeFiContract>, UserAccount) {
     let root = init_simulator(None);
-    // uses default values for deposit and gas
-    let ft = deploy!(
-        // Contract Proxy
-        contract: FtContract,
-        // Contract account id
-        contract_id: FT_ID,
-        // Bytes of contract
-        bytes: &FT_WASM_BYTES,
-        // User deploying the contract,
-        signer_account: root,
-        // init method
-        init_method: new_default_meta(
-            root.valid_account_id(),
-            initial_balance.into()
-        )
-    );
+    // The standard token has no proxy of its own. The Synchro token proxy only serves to call
+    // the NEP-141 methods both of them implement.
+    let ft = ContractAccount {
+        user_account: deploy_ft(&root, initial_balance),
+        contract: FtContract { account_id: FT_ID.to_string() },
+    };
     let alice = root.create_user("alice".to_string(), to_yocto("100"));
     register_user(&alice);
 
@@ -92,3 +110,300 @@ pub fn init_with_macros(
 
     (root, ft, defi, alice)
 }
+
+pub fn init_gov(initial_balance: u128) -> (UserAccount, UserAccount, UserAccount, UserAccount) {
+    let (root, ft, alice) = init_no_macros(initial_balance);
+
+    let gov = root.deploy(&GOV_WASM_BYTES, GOV_ID.into(), STORAGE_AMOUNT);
+    gov.call(
+        GOV_ID.into(),
+        "new",
+        &json!({
+            "token_id": FT_ID,
+            "market_id": root.account_id(),
+            "min_create_poll_amount": GOV_MIN_CREATE_POLL_AMOUNT,
+            "poll_config": {
+                "voting_period": GOV_VOTING_PERIOD.to_string(),
+                "quorum": { "numerator": 1, "denominator": 10 },
+                "quorum_basis": "TotalSupply",
+                "pass_threshold": { "numerator": 1, "denominator": 2 },
+                "execution_delay": GOV_VOTING_PERIOD.to_string(),
+                "failed_deposit_policy": "Burn",
+                "conviction": {
+                    "lock_period": GOV_VOTING_PERIOD.to_string(),
+                    "max_conviction": GOV_MAX_CONVICTION,
+                },
+                "veto_deposit_policy": "Forfeit",
+            },
+        })
+        .to_string()
+        .into_bytes(),
+        DEFAULT_GAS / 2,
+        0, // attached deposit
+    )
+    .assert_success();
+    register_user(&gov);
+
+    (root, ft, gov, alice)
+}
+
+// Sends `amount` of FT from `user` to the gov contract with the given version 1 user action.
+pub fn gov_transfer_call(
+    user: &UserAccount,
+    amount: u128,
+    action: Value,
+) -> near_sdk_sim::ExecutionResult {
+    user.call(
+        FT_ID.into(),
+        "ft_transfer_call",
+        &json!({
+            "receiver_id": GOV_ID,
+            "amount": U128::from(amount),
+            "msg": json!({ "V1": action }).to_string(),
+        })
+        .to_string()
+        .into_bytes(),
+        DEFAULT_GAS,
+        1, // deposit
+    )
+}
+
+/// The contracts of `init_gov`, a Synchro staking contract and a stake pool, all owned by a
+/// 2-of-3 multisig. Returns `(root, gov, multisig, members)`.
+pub fn init_multisig_stack(
+    initial_balance: u128,
+    request_lifetime: u64,
+) -> (UserAccount, UserAccount, UserAccount, Vec<UserAccount>) {
+    let (root, _ft, gov, alice) = init_gov(initial_balance);
+    let members = vec![
+        alice,
+        root.create_user("bob".to_string(), to_yocto("100")),
+        root.create_user("carol".to_string(), to_yocto("100")),
+    ];
+
+    let multisig = root.deploy(&MULTISIG_WASM_BYTES, MULTISIG_ID.into(), STORAGE_AMOUNT);
+    multisig
+        .call(
+            MULTISIG_ID.into(),
+            "new",
+            &json!({
+                "members": members.iter().map(|member| member.valid_account_id()).collect::<Vec<_>>(),
+                "threshold": 2,
+                "request_lifetime": request_lifetime.to_string(),
+            })
+            .to_string()
+            .into_bytes(),
+            DEFAULT_GAS / 2,
+            0, // attached deposit
+        )
+        .assert_success();
+
+    // The gov contract initialized itself, so it hands its own owner role over.
+    gov.call(
+        GOV_ID.into(),
+        "set_owner",
+        &json!({ "new_owner": MULTISIG_ID }).to_string().into_bytes(),
+        DEFAULT_GAS / 2,
+        0, // attached deposit
+    )
+    .assert_success();
+
+    let staking = root.deploy(&SYNCHRO_WASM_BYTES, STAKING_ID.into(), STORAGE_AMOUNT);
+    staking
+        .call(
+            STAKING_ID.into(),
+            "new",
+            &json!({ "whitelist": [MULTISIG_ID], "locked_token": FT_ID }).to_string().into_bytes(),
+            DEFAULT_GAS / 2,
+            0, // attached deposit
+        )
+        .assert_success();
+    staking
+        .call(
+            STAKING_ID.into(),
+            "set_owner",
+            &json!({ "owner_id": MULTISIG_ID }).to_string().into_bytes(),
+            DEFAULT_GAS / 2,
+            0, // attached deposit
+        )
+        .assert_success();
+
+    let stake_pool = root.deploy(&STAKE_POOL_WASM_BYTES, STAKE_POOL_ID.into(), STORAGE_AMOUNT);
+    stake_pool
+        .call(
+            STAKE_POOL_ID.into(),
+            "new",
+            &json!({
+                "owner_id": MULTISIG_ID,
+                "stake_public_key": "ed25519:6E8sCci9badyRkXb3JoRpBj5p8C6Tw41ELDZoiihKEtp",
+                "reward_fee_fraction": { "numerator": 10, "denominator": 100 },
+                "token_contract": FT_ID,
+                "asset": { "Token": { "token_id": FT_ID } },
+            })
+            .to_string()
+            .into_bytes(),
+            DEFAULT_GAS / 2,
+            0, // attached deposit
+        )
+        .assert_success();
+
+    (root, gov, multisig, members)
+}
+
+/// The contracts of `init_gov` with a mock market that is the market of the gov contract.
+/// Returns `(root, gov, market, alice)`.
+pub fn init_market(initial_balance: u128) -> (UserAccount, UserAccount, UserAccount, UserAccount) {
+    let (root, _ft, gov, alice) = init_gov(initial_balance);
+
+    let market = root.deploy(&MARKET_WASM_BYTES, MARKET_ID.into(), STORAGE_AMOUNT);
+    market
+        .call(
+            MARKET_ID.into(),
+            "new",
+            &json!({ "gov_id": GOV_ID }).to_string().into_bytes(),
+            DEFAULT_GAS / 2,
+            0, // attached deposit
+        )
+        .assert_success();
+    gov.call(
+        GOV_ID.into(),
+        "set_market_id",
+        &json!({ "account": MARKET_ID }).to_string().into_bytes(),
+        DEFAULT_GAS / 2,
+        0, // attached deposit
+    )
+    .assert_success();
+
+    (root, gov, market, alice)
+}
+
+/// A stake pool owned by root with `initial_balance` of its own, taking deposits of `asset`.
+/// It mints its liquid staking token at `LIQUID_TOKEN_ID` and is registered with `synchro-token`.
+/// Returns `(root, pool, alice)`.
+pub fn init_stake_pool(initial_balance: u128, asset: Value) -> (UserAccount, UserAccount, UserAccount) {
+    let (root, _ft, alice) = init_no_macros(to_yocto("100000"));
+    let pool = deploy_stake_pool(&root, initial_balance, asset);
+
+    (root, pool, alice)
+}
+
+/// The contracts of `init_gov` with a stake pool of the voting token owned by root as their stake
+/// source. Returns `(root, gov, pool, alice)`.
+pub fn init_gov_with_stake_pool(
+    initial_balance: u128,
+) -> (UserAccount, UserAccount, UserAccount, UserAccount) {
+    let (root, _ft, gov, alice) = init_gov(initial_balance);
+    let pool = deploy_stake_pool(&root, to_yocto("100"), json!({ "Token": { "token_id": FT_ID } }));
+
+    gov.call(
+        GOV_ID.into(),
+        "set_stake_sources",
+        &json!({ "stake_sources": [{ "PairStakePool": { "contract_id": STAKE_POOL_ID } }] })
+            .to_string()
+            .into_bytes(),
+        DEFAULT_GAS / 2,
+        0, // attached deposit
+    )
+    .assert_success();
+    root.call(
+        STAKE_POOL_ID.into(),
+        "set_vote_locker",
+        &json!({ "vote_locker": GOV_ID }).to_string().into_bytes(),
+        DEFAULT_GAS / 2,
+        0, // attached deposit
+    )
+    .assert_success();
+
+    (root, gov, pool, alice)
+}
+
+/// Deploys a native stake pool owned by root at `account_id`. It shares the liquid token of the
+/// pool of `deploy_stake_pool` without being its minter, so it can't take stake.
+pub fn deploy_native_pool(root: &UserAccount, account_id: &str) -> UserAccount {
+    let pool = root.deploy(&STAKE_POOL_WASM_BYTES, account_id.into(), to_yocto("100"));
+    pool.call(
+        account_id.into(),
+        "new",
+        &json!({
+            "owner_id": root.account_id(),
+            "stake_public_key": "ed25519:6E8sCci9badyRkXb3JoRpBj5p8C6Tw41ELDZoiihKEtp",
+            "reward_fee_fraction": { "numerator": 10, "denominator": 100 },
+            "token_contract": LIQUID_TOKEN_ID,
+            "asset": "Native",
+        })
+        .to_string()
+        .into_bytes(),
+        DEFAULT_GAS / 2,
+        0, // attached deposit
+    )
+    .assert_success();
+
+    pool
+}
+
+/// Deploys a stake pool owned by root, with its liquid token, next to the contracts of
+/// `init_no_macros`.
+fn deploy_stake_pool(root: &UserAccount, initial_balance: u128, asset: Value) -> UserAccount {
+    // The liquid token initializes itself, so it stays its own owner.
+    let liquid_token = root.deploy(&SYNCHRO_WASM_BYTES, LIQUID_TOKEN_ID.into(), STORAGE_AMOUNT);
+    liquid_token
+        .call(
+            LIQUID_TOKEN_ID.into(),
+            "new",
+            &json!({ "whitelist": [], "locked_token": FT_ID }).to_string().into_bytes(),
+            DEFAULT_GAS / 2,
+            0, // attached deposit
+        )
+        .assert_success();
+
+    let pool = root.deploy(&STAKE_POOL_WASM_BYTES, STAKE_POOL_ID.into(), initial_balance);
+    pool.call(
+        STAKE_POOL_ID.into(),
+        "new",
+        &json!({
+            "owner_id": root.account_id(),
+            "stake_public_key": "ed25519:6E8sCci9badyRkXb3JoRpBj5p8C6Tw41ELDZoiihKEtp",
+            "reward_fee_fraction": { "numerator": 10, "denominator": 100 },
+            "token_contract": LIQUID_TOKEN_ID,
+            "asset": asset,
+        })
+        .to_string()
+        .into_bytes(),
+        DEFAULT_GAS / 2,
+        0, // attached deposit
+    )
+    .assert_success();
+    liquid_token
+        .call(
+            LIQUID_TOKEN_ID.into(),
+            "set_minter",
+            &json!({ "minter": STAKE_POOL_ID }).to_string().into_bytes(),
+            DEFAULT_GAS / 2,
+            0, // attached deposit
+        )
+        .assert_success();
+    // The pool locks the receipts it counts as voting power.
+    liquid_token
+        .call(
+            LIQUID_TOKEN_ID.into(),
+            "set_vote_locker",
+            &json!({ "vote_locker": STAKE_POOL_ID }).to_string().into_bytes(),
+            DEFAULT_GAS / 2,
+            0, // attached deposit
+        )
+        .assert_success();
+    // Registered by root, so the balance of the pool only changes with deposits and rewards.
+    // The pool takes the receipts it redeems with the liquid token.
+    for token_id in [FT_ID, LIQUID_TOKEN_ID].iter() {
+        root.call(
+            token_id.to_string(),
+            "storage_deposit",
+            &json!({ "account_id": STAKE_POOL_ID }).to_string().into_bytes(),
+            DEFAULT_GAS / 2,
+            near_sdk::env::storage_byte_cost() * 125, // attached deposit
+        )
+        .assert_success();
+    }
+
+    pool
+}